@@ -16,6 +16,25 @@ pub struct NotEnoughElements;
 #[derive(Debug)]
 pub struct InvalidHex;
 
+#[derive(Debug)]
+pub struct InvalidStop {
+    /// the index of the offending stop
+    pub index: usize,
+    pub reason: InvalidStopReason,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidStopReason {
+    /// the gradient has no stops at all
+    TooFewStops,
+    /// the position is not in `[0, 1]`
+    OutOfRange,
+    /// the position is the same as the position of the previous stop
+    DuplicatePosition,
+    /// the position is smaller than the position of the previous stop
+    Unsorted,
+}
+
 impl std::fmt::Display for NotEnoughElements {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "not enough elements")
@@ -28,8 +47,21 @@ impl std::fmt::Display for InvalidHex {
     }
 }
 
+impl std::fmt::Display for InvalidStop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self.reason {
+            InvalidStopReason::TooFewStops => "gradient requires at least one stop",
+            InvalidStopReason::OutOfRange => "position is not in [0, 1]",
+            InvalidStopReason::DuplicatePosition => "position is the same as the previous stop",
+            InvalidStopReason::Unsorted => "position is smaller than the previous stop",
+        };
+        write!(f, "invalid stop {}: {}", self.index, reason)
+    }
+}
+
 impl std::error::Error for NotEnoughElements {}
 impl std::error::Error for InvalidHex {}
+impl std::error::Error for InvalidStop {}
 
 //
 // traits
@@ -77,6 +109,12 @@ pub trait Color4: Color<4> {
     }
 }
 
+/// a color that can be converted into every `GradientSpace`
+pub trait GradientColor<const N: usize>: Color<N> {
+    fn to_space(self, space: GradientSpace) -> [f32; N];
+    fn from_space(values: [f32; N], space: GradientSpace) -> Self;
+}
+
 pub trait ByteColor<const N: usize>: Color<N> {
     fn from_u8(v: [u8; N]) -> Self {
         let mut channels = [0.0; N];
@@ -98,31 +136,48 @@ pub trait ByteColor<const N: usize>: Color<N> {
         bytes
     }
 
+    /// parses `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa`. the `#` is optional. short forms are
+    /// expanded by duplicating each digit, such that `#f80` equals `#ff8800`. colors with alpha
+    /// may omit it, in which case it is 255. colors without alpha reject hex with alpha.
     fn from_hex(hex: impl AsRef<str>) -> Result<Self, InvalidHex> {
         let hex = hex.as_ref();
-        let offset = if hex.starts_with('#') { 1 } else { 0 };
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
 
-        let hex_chars = hex.chars().collect::<Vec<_>>();
+        let mut digits = Vec::with_capacity(hex.len());
+        for c in hex.chars() {
+            let digit = c.to_digit(16).ok_or(InvalidHex)?;
+            digits.push(digit as u8);
+        }
 
-        let mut channels = [0u8; N];
-        for (i, item) in channels.iter_mut().enumerate() {
-            let m = 2 * i + offset;
-            let n = m + 1;
+        let (channel_count, is_short) = match digits.len() {
+            3 | 4 => (digits.len(), true),
+            6 | 8 => (digits.len() / 2, false),
+            _ => return Err(InvalidHex),
+        };
 
-            let a = u8::from_str_radix(&hex_chars[m].to_string(), 16).map_err(|_| InvalidHex)?;
-            let b = u8::from_str_radix(&hex_chars[n].to_string(), 16).map_err(|_| InvalidHex)?;
+        let alpha_is_omitted = N == 4 && channel_count == 3;
+        if channel_count != N && !alpha_is_omitted {
+            return Err(InvalidHex);
+        }
 
-            let entry = (a << 4) | b;
-            *item = entry;
+        let mut channels = [255u8; N];
+        for (i, item) in channels.iter_mut().take(channel_count).enumerate() {
+            *item = if is_short {
+                digits[i] * 0x11
+            } else {
+                (digits[2 * i] << 4) | digits[2 * i + 1]
+            };
         }
 
         Ok(Self::from_u8(channels))
     }
 
+    /// returns `#rrggbb`, or `#rrggbbaa` for colors with alpha, in lowercase. the result can be
+    /// parsed by `from_hex` without loss.
     fn to_hex(self) -> String {
         let mut hex = "#".to_string();
         for byte in self.to_u8() {
-            let value = format!("{:02X}", byte);
+            let value = format!("{:02x}", byte);
             hex.push_str(&value)
         }
 
@@ -137,6 +192,9 @@ pub trait ByteColor<const N: usize>: Color<N> {
 /// r: red
 /// g: green
 /// b: blue
+///
+/// the channels are gamma encoded srgb, like they are in hex codes and 8 bit images. use
+/// `to_linear` before doing math that expects linear light.
 #[derive(Debug, Default, Clone, Copy)]
 pub struct Rgb(pub f32, pub f32, pub f32);
 
@@ -156,6 +214,12 @@ pub struct OkLab(pub f32, pub f32, pub f32);
 #[derive(Debug, Default, Clone, Copy)]
 pub struct OkLch(pub f32, pub f32, pub f32);
 
+/// h: hue in degrees, in `[0, 360)`
+/// s: saturation
+/// v: value
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Hsv(pub f32, pub f32, pub f32);
+
 /// r: red
 /// g: green
 /// b: blue
@@ -233,7 +297,7 @@ impl Rgb {
 
 impl From<Rgb> for OkLab {
     fn from(value: Rgb) -> Self {
-        let c = value;
+        let c = value.to_linear();
 
         let l = 0.4122214708 * c.0 + 0.5363325363 * c.1 + 0.0514459929 * c.2;
         let m = 0.2119034982 * c.0 + 0.6806995451 * c.1 + 0.1073969566 * c.2;
@@ -263,11 +327,81 @@ impl From<OkLab> for Rgb {
         let m = m_ * m_ * m_;
         let s = s_ * s_ * s_;
 
-        Self(
+        Rgb::from_linear(Self(
             4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
             -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
             -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
-        )
+        ))
+    }
+}
+
+impl Rgb {
+    /// decodes the srgb transfer function, returning the channels in linear light. channels
+    /// outside of `[0, 1]` are extended symmetrically around 0.
+    pub fn to_linear(self) -> Rgb {
+        let decode = |x: f32| {
+            let y = x.abs();
+            let y = if y <= 0.04045 {
+                y / 12.92
+            } else {
+                f32::powf((y + 0.055) / 1.055, 2.4)
+            };
+            y.copysign(x)
+        };
+
+        Rgb(decode(self.0), decode(self.1), decode(self.2))
+    }
+
+    /// the inverse of `to_linear`. encodes linear light with the srgb transfer function.
+    pub fn from_linear(value: Rgb) -> Rgb {
+        let encode = |x: f32| {
+            let y = x.abs();
+            let y = if y <= 0.0031308 {
+                y * 12.92
+            } else {
+                1.055 * f32::powf(y, 1.0 / 2.4) - 0.055
+            };
+            y.copysign(x)
+        };
+
+        Rgb(encode(value.0), encode(value.1), encode(value.2))
+    }
+
+    /// converts an oklab color to rgb, like `Rgb::from`. but if the color lies outside of the rgb
+    /// gamut, its chroma is reduced until it fits. this preserves the lightness and the hue,
+    /// whereas clamping each channel individually, like `to_u8` does, may shift the hue of
+    /// saturated colors.
+    pub fn from_oklab_clipped(value: OkLab) -> Self {
+        const ITERATIONS: usize = 16;
+
+        let rgb = Rgb::from(value);
+        if rgb.is_in_gamut() {
+            return rgb;
+        }
+
+        let OkLch(l, c, h) = OkLch::from(value);
+        let l = l.clamp(0.0, 1.0);
+
+        // binary search for the largest chroma that is still in gamut
+        let mut min = 0.0;
+        let mut max = c;
+        for _ in 0..ITERATIONS {
+            let mid = (min + max) / 2.0;
+            if Rgb::from(OkLch(l, mid, h)).is_in_gamut() {
+                min = mid;
+            } else {
+                max = mid;
+            }
+        }
+
+        // get rid of floating point errors, for example when the chroma was reduced to 0
+        let Rgb(r, g, b) = Rgb::from(OkLch(l, min, h));
+        Rgb(r.clamp(0.0, 1.0), g.clamp(0.0, 1.0), b.clamp(0.0, 1.0))
+    }
+
+    pub fn is_in_gamut(self) -> bool {
+        let range = 0.0..=1.0;
+        range.contains(&self.0) && range.contains(&self.1) && range.contains(&self.2)
     }
 }
 
@@ -284,7 +418,7 @@ impl From<OkLch> for OkLab {
 impl From<OkLab> for OkLch {
     fn from(value: OkLab) -> Self {
         let l = value.0;
-        let c = f32::sqrt(value.1 * value.1 + value.2 * value.2);
+        let c = f32::hypot(value.1, value.2);
         let h = f32::atan2(value.2, value.1);
 
         Self(l, c, h)
@@ -303,6 +437,58 @@ impl From<OkLch> for Rgb {
     }
 }
 
+impl From<Rgb> for Hsv {
+    fn from(value: Rgb) -> Self {
+        let Rgb(r, g, b) = value;
+
+        let max = f32::max(r, f32::max(g, b));
+        let min = f32::min(r, f32::min(g, b));
+        let delta = max - min;
+
+        // the hue of grays, including black and white, is undefined
+        let mut h = if delta <= 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / delta).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / delta + 2.0)
+        } else {
+            60.0 * ((r - g) / delta + 4.0)
+        };
+
+        // rem_euclid may round up to 6 for tiny negative values
+        if h >= 360.0 {
+            h -= 360.0;
+        }
+
+        let s = if max <= 0.0 { 0.0 } else { delta / max };
+
+        Self(h, s, max)
+    }
+}
+
+impl From<Hsv> for Rgb {
+    fn from(value: Hsv) -> Self {
+        let Hsv(h, s, v) = value;
+
+        let h = h.rem_euclid(360.0) / 60.0;
+        let c = v * s;
+        let x = c * (1.0 - f32::abs(h % 2.0 - 1.0));
+        let m = v - c;
+
+        let (r, g, b) = match h as usize {
+            0 => (c, x, 0.0),
+            1 => (x, c, 0.0),
+            2 => (0.0, c, x),
+            3 => (0.0, x, c),
+            4 => (x, 0.0, c),
+            _ => (c, 0.0, x),
+        };
+
+        Self(r + m, g + m, b + m)
+    }
+}
+
 impl From<Rgba> for OkLaba {
     fn from(value: Rgba) -> Self {
         let OkLab(l, a, b) = OkLab::from(value.without_alpha());
@@ -345,6 +531,20 @@ impl From<OkLcha> for Rgba {
     }
 }
 
+/// the color is opaque, meaning its alpha is 1
+impl From<Rgb> for Rgba {
+    fn from(value: Rgb) -> Self {
+        value.with_alpha(1.0)
+    }
+}
+
+/// discards the alpha
+impl From<Rgba> for Rgb {
+    fn from(value: Rgba) -> Self {
+        value.without_alpha()
+    }
+}
+
 //
 // components
 //
@@ -427,6 +627,32 @@ impl OkLch {
     }
 }
 
+impl Hsv {
+    pub fn h(self) -> f32 {
+        self.0
+    }
+
+    pub fn s(self) -> f32 {
+        self.1
+    }
+
+    pub fn v(self) -> f32 {
+        self.2
+    }
+
+    pub fn set_h(&mut self, h: f32) {
+        self.0 = h;
+    }
+
+    pub fn set_s(&mut self, s: f32) {
+        self.1 = s;
+    }
+
+    pub fn set_v(&mut self, v: f32) {
+        self.2 = v;
+    }
+}
+
 impl Rgba {
     pub fn r(self) -> f32 {
         self.0
@@ -613,6 +839,34 @@ impl std::ops::IndexMut<usize> for OkLch {
     }
 }
 
+impl std::ops::Index<usize> for Hsv {
+    type Output = f32;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        debug_assert!(index < 3);
+
+        match index {
+            0 => &self.0,
+            1 => &self.1,
+            2 => &self.2,
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl std::ops::IndexMut<usize> for Hsv {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        debug_assert!(index < 3);
+
+        match index {
+            0 => &mut self.0,
+            1 => &mut self.1,
+            2 => &mut self.2,
+            _ => unreachable!(),
+        }
+    }
+}
+
 impl std::ops::Index<usize> for Rgba {
     type Output = f32;
 
@@ -711,12 +965,103 @@ impl Rgb {
     pub fn with_alpha(self, alpha: f32) -> Rgba {
         Rgba(self.0, self.1, self.2, alpha)
     }
+
+    /// same as `OkLab::distance`
+    pub fn distance(self, other: Rgb) -> f32 {
+        OkLab::from(self).distance(OkLab::from(other))
+    }
+
+    /// the relative luminance, as defined by wcag: <https://www.w3.org/TR/WCAG21/#dfn-relative-luminance>
+    pub fn luminance(self) -> f32 {
+        let Rgb(r, g, b) = self.to_linear();
+        0.2126 * r + 0.7152 * g + 0.0722 * b
+    }
+
+    /// the contrast ratio, as defined by wcag. ranges from 1 for identical colors to 21 for black
+    /// and white. the order of the colors doesn't matter.
+    pub fn contrast_ratio(self, other: Rgb) -> f32 {
+        let a = self.luminance();
+        let b = other.luminance();
+        let lighter = f32::max(a, b);
+        let darker = f32::min(a, b);
+        (lighter + 0.05) / (darker + 0.05)
+    }
+
+    /// returns black or white, whichever has the higher contrast when drawn on top of this color
+    pub fn pick_legible_text_color(self) -> Rgb {
+        let black = Rgb::black();
+        let white = Rgb::white();
+        if self.contrast_ratio(black) >= self.contrast_ratio(white) {
+            black
+        } else {
+            white
+        }
+    }
+
+    /// same as `to_u8`, but applies `x.powf(1.0 / gamma)` to each channel first, to bake a display
+    /// gamma for targets that are not srgb. a gamma of 1 is the same as `to_u8`.
+    pub fn to_u8_gamma(self, gamma: f32) -> [u8; 3] {
+        let encode = |x: f32| f32::powf(x.clamp(0.0, 1.0), 1.0 / gamma);
+        Rgb(encode(self.0), encode(self.1), encode(self.2)).to_u8()
+    }
+
+    /// composites `over` on top of this color. the blending happens in linear light.
+    pub fn blend(self, over: Rgb, mode: BlendMode) -> Rgb {
+        let a = self.to_linear();
+        let b = over.to_linear();
+
+        let blend = |a: f32, b: f32| match mode {
+            BlendMode::Normal => b,
+            BlendMode::Multiply => a * b,
+            BlendMode::Screen => 1.0 - (1.0 - a) * (1.0 - b),
+            BlendMode::Overlay => {
+                if a < 0.5 {
+                    2.0 * a * b
+                } else {
+                    1.0 - 2.0 * (1.0 - a) * (1.0 - b)
+                }
+            }
+        };
+
+        Rgb::from_linear(Rgb(blend(a.0, b.0), blend(a.1, b.1), blend(a.2, b.2)))
+    }
+
+    /// linearly interpolates each channel. `t` is clamped to `[0, 1]`. mixing in oklab usually
+    /// produces nicer transitions, see `OkLab::mix`.
+    pub fn mix(a: Rgb, b: Rgb, t: f32) -> Rgb {
+        let t = t.clamp(0.0, 1.0);
+        Rgb(
+            crate::common::mix(a.0, b.0, t),
+            crate::common::mix(a.1, b.1, t),
+            crate::common::mix(a.2, b.2, t),
+        )
+    }
 }
 
 impl OkLab {
     pub fn with_alpha(self, alpha: f32) -> OkLaba {
         OkLaba(self.0, self.1, self.2, alpha)
     }
+
+    /// the euclidean distance in oklab, which roughly corresponds to how different the colors are
+    /// perceived
+    pub fn distance(self, other: OkLab) -> f32 {
+        let dl = self.0 - other.0;
+        let da = self.1 - other.1;
+        let db = self.2 - other.2;
+        f32::sqrt(dl * dl + da * da + db * db)
+    }
+
+    /// linearly interpolates each channel, like `Gradient` does between two stops. `t` is clamped
+    /// to `[0, 1]`.
+    pub fn mix(a: OkLab, b: OkLab, t: f32) -> OkLab {
+        let t = t.clamp(0.0, 1.0);
+        OkLab(
+            crate::common::mix(a.0, b.0, t),
+            crate::common::mix(a.1, b.1, t),
+            crate::common::mix(a.2, b.2, t),
+        )
+    }
 }
 
 impl OkLch {
@@ -777,6 +1122,16 @@ impl Color<3> for OkLch {
     }
 }
 
+impl Color<3> for Hsv {
+    fn from_f32(values: [f32; 3]) -> Self {
+        Self(values[0], values[1], values[2])
+    }
+
+    fn to_f32(self) -> [f32; 3] {
+        [self.0, self.1, self.2]
+    }
+}
+
 impl Color<4> for Rgba {
     fn from_f32(values: [f32; 4]) -> Self {
         Self(values[0], values[1], values[2], values[3])
@@ -810,6 +1165,7 @@ impl Color<4> for OkLcha {
 impl Color3 for Rgb {}
 impl Color3 for OkLab {}
 impl Color3 for OkLch {}
+impl Color3 for Hsv {}
 impl Color4 for Rgba {}
 impl Color4 for OkLaba {}
 impl Color4 for OkLcha {}
@@ -817,45 +1173,308 @@ impl Color4 for OkLcha {}
 impl ByteColor<3> for Rgb {}
 impl ByteColor<4> for Rgba {}
 
+impl<T: Color<3> + Into<OkLab> + From<OkLab>> GradientColor<3> for T {
+    fn to_space(self, space: GradientSpace) -> [f32; 3] {
+        let oklab = self.into();
+        match space {
+            GradientSpace::Rgb => Rgb::from(oklab).to_f32(),
+            GradientSpace::OkLab => oklab.to_f32(),
+            GradientSpace::OkLch => OkLch::from(oklab).to_f32(),
+        }
+    }
+
+    fn from_space(values: [f32; 3], space: GradientSpace) -> Self {
+        let oklab = match space {
+            GradientSpace::Rgb => OkLab::from(Rgb::from_f32(values)),
+            GradientSpace::OkLab => OkLab::from_f32(values),
+            GradientSpace::OkLch => OkLab::from(OkLch::from_f32(values)),
+        };
+        Self::from(oklab)
+    }
+}
+
+impl<T: Color<4> + Into<OkLaba> + From<OkLaba>> GradientColor<4> for T {
+    fn to_space(self, space: GradientSpace) -> [f32; 4] {
+        let oklaba = self.into();
+        match space {
+            GradientSpace::Rgb => Rgba::from(oklaba).to_f32(),
+            GradientSpace::OkLab => oklaba.to_f32(),
+            GradientSpace::OkLch => OkLcha::from(oklaba).to_f32(),
+        }
+    }
+
+    fn from_space(values: [f32; 4], space: GradientSpace) -> Self {
+        let oklaba = match space {
+            GradientSpace::Rgb => OkLaba::from(Rgba::from_f32(values)),
+            GradientSpace::OkLab => OkLaba::from_f32(values),
+            GradientSpace::OkLch => OkLaba::from(OkLcha::from_f32(values)),
+        };
+        Self::from(oklaba)
+    }
+}
+
 //
 // other
 //
 
+/// how `Rgb::blend` composites a color on top of another
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BlendMode {
+    /// the color on top replaces the one below
+    #[default]
+    Normal,
+    /// darkens. useful to shade terrain with a hillshade
+    Multiply,
+    /// lightens, the inverse of multiply
+    Screen,
+    /// multiplies dark and screens light areas of the color below, increasing contrast
+    Overlay,
+}
+
+/// the color space in which a gradient mixes adjacent colors
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GradientSpace {
+    /// mixes the channels of rgb directly. harsher transitions, which may appear darker in the
+    /// middle.
+    Rgb,
+    /// perceptually even transitions
+    #[default]
+    OkLab,
+    /// mixes lightness, chroma and hue separately. the hue is rotated the short way around the
+    /// color wheel, which produces more saturated transitions than oklab.
+    OkLch,
+}
+
+/// how a gradient samples positions outside of `[0, 1]`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GradientEdge {
+    /// returns the color of the first or last stop
+    #[default]
+    Clamp,
+    /// repeats the gradient, such that 1.25 samples the same color as 0.25. note that this makes
+    /// 1.0 sample the first stop, not the last
+    Wrap,
+    /// repeats the gradient, reversing every other repetition, such that 1.25 samples the same
+    /// color as 0.75
+    Mirror,
+}
+
+/// maps a value in `[0, 1]` to a color, by mixing the two closest stops. `N` is the number of
+/// channels of `T`, not the number of stops. the stops are stored in a `Vec`, so their number may
+/// be decided at runtime, for example by a palette loaded from a file.
 #[derive(Debug, Default, Clone)]
-pub struct Gradient<T: Color<N>, const N: usize>(Vec<T>);
+pub struct Gradient<T: Color<N>, const N: usize> {
+    colors: Vec<T>,
+    positions: Vec<f32>,
+    space: GradientSpace,
+    edge: GradientEdge,
+}
 
 impl<T: Color<N>, const N: usize> Gradient<T, N> {
-    pub fn try_from(value: impl IntoIterator<Item = T>) -> Result<Self, NotEnoughElements> {
+    /// creates a gradient, where the colors are evenly spaced across `[0, 1]`
+    pub fn try_from(value: impl IntoIterator<Item = T>) -> Result<Self, InvalidStop> {
         let colors = value.into_iter().collect::<Vec<_>>();
         if colors.is_empty() {
-            Err(NotEnoughElements)
+            return Err(InvalidStop {
+                index: 0,
+                reason: InvalidStopReason::TooFewStops,
+            });
+        }
+
+        let splits = usize::max(colors.len() - 1, 1) as f32;
+        let positions = (0..colors.len()).map(|i| i as f32 / splits).collect();
+
+        Ok(Self {
+            colors,
+            positions,
+            space: GradientSpace::default(),
+            edge: GradientEdge::default(),
+        })
+    }
+
+    /// creates a gradient, where each color is placed at the given position. the positions must
+    /// be in `[0, 1]` and strictly increasing. below the first and above the last position, the
+    /// first and last color are returned respectively.
+    pub fn with_stops(stops: impl IntoIterator<Item = (f32, T)>) -> Result<Self, InvalidStop> {
+        let (positions, colors): (Vec<_>, Vec<_>) = stops.into_iter().unzip();
+        if colors.is_empty() {
+            return Err(InvalidStop {
+                index: 0,
+                reason: InvalidStopReason::TooFewStops,
+            });
+        }
+
+        for (index, &position) in positions.iter().enumerate() {
+            let reason = if !(0.0..=1.0).contains(&position) {
+                Some(InvalidStopReason::OutOfRange)
+            } else if index == 0 {
+                None
+            } else if position == positions[index - 1] {
+                Some(InvalidStopReason::DuplicatePosition)
+            } else if position < positions[index - 1] {
+                Some(InvalidStopReason::Unsorted)
+            } else {
+                None
+            };
+
+            if let Some(reason) = reason {
+                return Err(InvalidStop { index, reason });
+            }
+        }
+
+        Ok(Self {
+            colors,
+            positions,
+            space: GradientSpace::default(),
+            edge: GradientEdge::default(),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    pub fn with_space(self, space: GradientSpace) -> Self {
+        Self { space, ..self }
+    }
+
+    pub fn space(&self) -> GradientSpace {
+        self.space
+    }
+
+    /// returns a gradient, where `sample(x)` returns the same color as `sample(1.0 - x)` of this
+    /// gradient
+    pub fn reversed(&self) -> Self {
+        let colors = self.colors.iter().rev().copied().collect();
+        let positions = self.positions.iter().rev().map(|x| 1.0 - x).collect();
+
+        Self {
+            colors,
+            positions,
+            space: self.space,
+            edge: self.edge,
+        }
+    }
+
+    pub fn with_edge(self, edge: GradientEdge) -> Self {
+        Self { edge, ..self }
+    }
+
+    pub fn edge(&self) -> GradientEdge {
+        self.edge
+    }
+
+    /// returns the color of the stop closest to `x`, instead of mixing the two closest stops.
+    /// this produces distinct bands of color.
+    pub fn quantize(&self, x: f32) -> T {
+        let x = self.apply_edge(x);
+        let upper = self.upper_stop(x);
+
+        if upper == 0 {
+            return self.colors[0];
+        }
+
+        if upper == self.colors.len() {
+            let last_index = self.colors.len() - 1;
+            return self.colors[last_index];
+        }
+
+        let lower = upper - 1;
+        if x - self.positions[lower] <= self.positions[upper] - x {
+            self.colors[lower]
         } else {
-            Ok(Self(colors))
+            self.colors[upper]
         }
     }
 
-    pub fn sample(&self, x: f32) -> T {
-        if self.0.len() == 1 {
-            return self.0[0];
+    // index of the first stop after `x`. equals the number of stops if there is none
+    fn upper_stop(&self, x: f32) -> usize {
+        self.positions.partition_point(|&position| position <= x)
+    }
+
+    // whether `upper_stop(x)` would return `upper`
+    fn is_upper_stop(&self, x: f32, upper: usize) -> bool {
+        let is_after_lower = upper == 0 || self.positions[upper - 1] <= x;
+        let is_before_upper = upper == self.positions.len() || self.positions[upper] > x;
+        is_after_lower && is_before_upper
+    }
+
+    fn apply_edge(&self, x: f32) -> f32 {
+        match self.edge {
+            GradientEdge::Clamp => x,
+            GradientEdge::Wrap => x.rem_euclid(1.0),
+            GradientEdge::Mirror => {
+                let x = x.rem_euclid(2.0);
+                if x > 1.0 {
+                    2.0 - x
+                } else {
+                    x
+                }
+            }
         }
+    }
+}
 
-        if x <= 0.0 {
-            return self.0[0];
+impl<T: GradientColor<N>, const N: usize> Gradient<T, N> {
+    pub fn sample(&self, x: f32) -> T {
+        let x = self.apply_edge(x);
+        let upper = self.upper_stop(x);
+        self.sample_between(x, upper, |i| self.colors[i].to_space(self.space))
+    }
+
+    /// same as calling `sample` for each height, but the colors are converted into the gradient
+    /// space only once, and the two stops around a height are searched only when it leaves the
+    /// stops of the previous height. this is fastest when neighbouring heights are similar, like
+    /// in a row of a height map. panics if `heights` and `out` have different lengths.
+    pub fn sample_into(&self, heights: &[f32], out: &mut [T]) {
+        assert_eq!(heights.len(), out.len());
+
+        let colors = self
+            .colors
+            .iter()
+            .map(|x| x.to_space(self.space))
+            .collect::<Vec<_>>();
+
+        let mut upper = 0;
+        for (&x, out) in heights.iter().zip(out.iter_mut()) {
+            let x = self.apply_edge(x);
+            if !self.is_upper_stop(x, upper) {
+                upper = self.upper_stop(x);
+            }
+
+            *out = self.sample_between(x, upper, |i| colors[i]);
         }
+    }
 
-        if x >= 1.0 {
-            let last_index = self.0.len() - 1;
-            return self.0[last_index];
+    // `upper` is the index of the first stop after `x`, and `color(i)` returns the color of the
+    // stop `i`, converted into the gradient space
+    fn sample_between(&self, x: f32, upper: usize, color: impl Fn(usize) -> [f32; N]) -> T {
+        if upper == 0 {
+            return self.colors[0];
         }
 
-        let splits = (self.0.len() - 1) as f32;
-        let scaled = x * splits;
-        let lower = scaled.floor() as usize;
-        let upper = scaled.ceil() as usize;
-        let lerp = scaled % 1.0;
+        if upper == self.colors.len() {
+            let last_index = self.colors.len() - 1;
+            return self.colors[last_index];
+        }
 
-        let color_1 = self.0[lower].to_f32();
-        let color_2 = self.0[upper].to_f32();
+        let lower = upper - 1;
+        let lower_position = self.positions[lower];
+        let upper_position = self.positions[upper];
+        let lerp = (x - lower_position) / (upper_position - lower_position);
+
+        let color_1 = color(lower);
+        let mut color_2 = color(upper);
+
+        // rotate the hue the short way around
+        if self.space == GradientSpace::OkLch {
+            let delta = color_2[2] - color_1[2];
+            if delta > std::f32::consts::PI {
+                color_2[2] -= std::f32::consts::TAU;
+            } else if delta < -std::f32::consts::PI {
+                color_2[2] += std::f32::consts::TAU;
+            }
+        }
 
         let mut mix = [0.0; N];
         for i in 0..N {
@@ -864,6 +1483,537 @@ impl<T: Color<N>, const N: usize> Gradient<T, N> {
             mix[i] = crate::common::mix(a, b, lerp);
         }
 
-        T::from_f32(mix)
+        T::from_space(mix, self.space)
+    }
+}
+
+/// colors for terrain. the first seven are the stops of the default height gradient, from the
+/// deepest ocean to the highest peaks.
+pub mod named {
+    use super::Rgb;
+
+    pub const OCEAN_DEEP: Rgb = Rgb(
+        0x33 as f32 / 255.0,
+        0x4e as f32 / 255.0,
+        0x9d as f32 / 255.0,
+    );
+    pub const OCEAN_SHALLOW: Rgb = Rgb(
+        0x1d as f32 / 255.0,
+        0x90 as f32 / 255.0,
+        0xff as f32 / 255.0,
+    );
+    pub const LOWLAND: Rgb = Rgb(
+        0x52 as f32 / 255.0,
+        0xd8 as f32 / 255.0,
+        0x3b as f32 / 255.0,
+    );
+    pub const HILLS: Rgb = Rgb(
+        0xe4 as f32 / 255.0,
+        0xf0 as f32 / 255.0,
+        0x35 as f32 / 255.0,
+    );
+    pub const HIGHLAND: Rgb = Rgb(
+        0xff as f32 / 255.0,
+        0x8b as f32 / 255.0,
+        0x00 as f32 / 255.0,
+    );
+    pub const MOUNTAIN: Rgb = Rgb(
+        0xb0 as f32 / 255.0,
+        0x1e as f32 / 255.0,
+        0x13 as f32 / 255.0,
+    );
+    pub const PEAK: Rgb = Rgb(
+        0x7c as f32 / 255.0,
+        0x2b as f32 / 255.0,
+        0x12 as f32 / 255.0,
+    );
+
+    pub const SAND: Rgb = Rgb(
+        0xe0 as f32 / 255.0,
+        0xcd as f32 / 255.0,
+        0x95 as f32 / 255.0,
+    );
+    pub const GRASS: Rgb = Rgb(
+        0x5a as f32 / 255.0,
+        0x8f as f32 / 255.0,
+        0x3c as f32 / 255.0,
+    );
+    pub const ROCK: Rgb = Rgb(
+        0x7d as f32 / 255.0,
+        0x74 as f32 / 255.0,
+        0x6b as f32 / 255.0,
+    );
+    pub const SNOW: Rgb = Rgb(
+        0xf4 as f32 / 255.0,
+        0xf7 as f32 / 255.0,
+        0xfa as f32 / 255.0,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn srgb_mid_gray_round_trip() {
+        let gray = Rgb(0.5, 0.5, 0.5);
+
+        // 0.5 in srgb is 0.2140 in linear light
+        let linear = gray.to_linear();
+        for c in [linear.0, linear.1, linear.2] {
+            assert!((c - 0.214_041).abs() < 0.000_01, "{}", c);
+        }
+
+        let Rgb(r, g, b) = Rgb::from_linear(linear);
+        for c in [r, g, b] {
+            assert!((c - 0.5).abs() < 0.000_01, "{}", c);
+        }
+    }
+
+    fn assert_rgb_eq(actual: Rgb, expected: Rgb, epsilon: f32) {
+        let is_equal = (actual.0 - expected.0).abs() < epsilon
+            && (actual.1 - expected.1).abs() < epsilon
+            && (actual.2 - expected.2).abs() < epsilon;
+        assert!(is_equal, "expected {:?}, got {:?}", expected, actual);
+    }
+
+    #[test]
+    fn gradient_midpoint_depends_on_space() {
+        let gradient = Gradient::try_from([Rgb::red(), Rgb::blue()]).unwrap();
+        let rgb = gradient.clone().with_space(GradientSpace::Rgb).sample(0.5);
+        let oklab = gradient
+            .clone()
+            .with_space(GradientSpace::OkLab)
+            .sample(0.5);
+        let oklch = gradient
+            .clone()
+            .with_space(GradientSpace::OkLch)
+            .sample(0.5);
+
+        // rgb mixes the gamma encoded channels directly
+        assert_rgb_eq(rgb, Rgb(0.5, 0.0, 0.5), 0.001);
+
+        assert!(rgb.distance(oklab) > 0.01, "{:?} {:?}", rgb, oklab);
+        assert!(rgb.distance(oklch) > 0.01, "{:?} {:?}", rgb, oklch);
+        assert!(oklab.distance(oklch) > 0.01, "{:?} {:?}", oklab, oklch);
+
+        // oklab cuts through the color wheel, while oklch goes around it and keeps the chroma
+        let chroma = |x: Rgb| OkLch::from(x).c();
+        assert!(chroma(oklch) > chroma(oklab));
+    }
+
+    #[test]
+    fn gradient_with_uneven_stops() {
+        let gradient =
+            Gradient::with_stops([(0.0, Rgb::black()), (0.1, Rgb::white()), (1.0, Rgb::red())])
+                .unwrap()
+                .with_space(GradientSpace::Rgb);
+
+        assert_rgb_eq(gradient.sample(0.0), Rgb::black(), 0.001);
+        assert_rgb_eq(gradient.sample(0.05), Rgb(0.5, 0.5, 0.5), 0.001);
+        assert_rgb_eq(gradient.sample(0.1), Rgb::white(), 0.001);
+        assert_rgb_eq(gradient.sample(0.55), Rgb(1.0, 0.5, 0.5), 0.001);
+        assert_rgb_eq(gradient.sample(1.0), Rgb::red(), 0.001);
+    }
+
+    #[test]
+    fn gradient_clamps_outside_of_stops() {
+        let gradient = Gradient::with_stops([(0.2, Rgb::black()), (0.8, Rgb::white())])
+            .unwrap()
+            .with_space(GradientSpace::Rgb);
+
+        for x in [-1.0, 0.0, 0.1, 0.2] {
+            assert_rgb_eq(gradient.sample(x), Rgb::black(), 0.001);
+        }
+
+        assert_rgb_eq(gradient.sample(0.5), Rgb(0.5, 0.5, 0.5), 0.001);
+
+        for x in [0.8, 0.9, 1.0, 2.0] {
+            assert_rgb_eq(gradient.sample(x), Rgb::white(), 0.001);
+        }
+    }
+
+    #[test]
+    fn gradient_edges() {
+        let gradient = Gradient::try_from([Rgb::black(), Rgb::white()])
+            .unwrap()
+            .with_space(GradientSpace::Rgb);
+        let gray = |x: f32| Rgb(x, x, x);
+
+        let expected = [
+            (GradientEdge::Clamp, [0.0, 1.0, 1.0]),
+            (GradientEdge::Wrap, [0.75, 0.25, 0.5]),
+            (GradientEdge::Mirror, [0.25, 0.75, 0.5]),
+        ];
+
+        for (edge, expected) in expected {
+            let gradient = gradient.clone().with_edge(edge);
+            for (x, expected) in [-0.25, 1.25, 2.5].into_iter().zip(expected) {
+                let actual = gradient.sample(x);
+                assert_rgb_eq(actual, gray(expected), 0.001);
+            }
+        }
+    }
+
+    #[test]
+    fn gradient_wrap_samples_first_stop_at_integers() {
+        let gradient = Gradient::try_from([Rgb::black(), Rgb::white()])
+            .unwrap()
+            .with_space(GradientSpace::Rgb)
+            .with_edge(GradientEdge::Wrap);
+
+        for x in [-1.0, 0.0, 1.0, 2.0] {
+            assert_rgb_eq(gradient.sample(x), Rgb::black(), 0.001);
+        }
+
+        // just below an integer is still close to the last stop
+        assert_rgb_eq(gradient.sample(0.999), Rgb(0.999, 0.999, 0.999), 0.001);
+    }
+
+    #[test]
+    fn from_hex_lengths() {
+        assert_eq!(Rgb::from_hex("#f80").unwrap().to_u8(), [0xff, 0x88, 0x00]);
+        assert_eq!(
+            Rgb::from_hex("#ff8800").unwrap().to_u8(),
+            [0xff, 0x88, 0x00]
+        );
+        assert_eq!(Rgb::from_hex("12aB9c").unwrap().to_u8(), [0x12, 0xab, 0x9c]);
+        assert!(Rgb::from_hex("#f808").is_err());
+        assert!(Rgb::from_hex("#ff880088").is_err());
+
+        let rgba = |hex| Rgba::from_hex(hex).unwrap().to_u8();
+        assert_eq!(rgba("#f80"), [0xff, 0x88, 0x00, 0xff]);
+        assert_eq!(rgba("#f808"), [0xff, 0x88, 0x00, 0x88]);
+        assert_eq!(rgba("#ff8800"), [0xff, 0x88, 0x00, 0xff]);
+        assert_eq!(rgba("#ff880012"), [0xff, 0x88, 0x00, 0x12]);
+    }
+
+    #[test]
+    fn from_hex_malformed() {
+        for hex in [
+            "",
+            "#",
+            "#ff",
+            "#fffff",
+            "#fffffff",
+            "#fffffffff",
+            "#ggg",
+            "#12345z",
+            "##fff",
+        ] {
+            assert!(Rgb::from_hex(hex).is_err(), "{}", hex);
+            assert!(Rgba::from_hex(hex).is_err(), "{}", hex);
+        }
+    }
+
+    #[test]
+    fn rgba_conversions() {
+        let rgba = Rgba::from(Rgb(0.1, 0.2, 0.3));
+        assert_eq!(rgba.to_f32(), [0.1, 0.2, 0.3, 1.0]);
+        assert_eq!(Rgba::from(Rgb::from_u8([1, 2, 3])).to_u8(), [1, 2, 3, 255]);
+
+        let rgb = Rgb::from(Rgba(0.1, 0.2, 0.3, 0.4));
+        assert_eq!(rgb.to_f32(), [0.1, 0.2, 0.3]);
+        assert_eq!(Rgb::from(Rgba::from_u8([1, 2, 3, 4])).to_u8(), [1, 2, 3]);
+
+        assert_eq!(
+            Rgb(0.1, 0.2, 0.3).with_alpha(0.4).to_f32(),
+            [0.1, 0.2, 0.3, 0.4]
+        );
+        assert_eq!(
+            Rgba(0.1, 0.2, 0.3, 0.4).without_alpha().to_f32(),
+            [0.1, 0.2, 0.3]
+        );
+    }
+
+    #[test]
+    fn oklab_oklch_round_trip() {
+        let colors = [
+            OkLab(0.5, 0.1, 0.05),
+            OkLab(0.7, -0.1, 0.1),
+            OkLab(0.3, -0.05, -0.2),
+            OkLab(0.9, 0.2, -0.1),
+            // zero chroma, where the hue is undefined
+            OkLab(0.5, 0.0, 0.0),
+            OkLab(0.0, 0.0, 0.0),
+            OkLab(1.0, 0.0, 0.0),
+        ];
+
+        for color in colors {
+            let oklch = OkLch::from(color);
+            assert!(oklch.c() >= 0.0);
+            assert!(oklch.h().is_finite());
+
+            let OkLab(l, a, b) = OkLab::from(oklch);
+            assert!((l - color.0).abs() < 0.000_01, "{:?} {}", color, l);
+            assert!((a - color.1).abs() < 0.000_01, "{:?} {}", color, a);
+            assert!((b - color.2).abs() < 0.000_01, "{:?} {}", color, b);
+        }
+    }
+
+    #[test]
+    fn hsv_corners() {
+        let corners = [
+            (Rgb::red(), 0.0),
+            (Rgb::yellow(), 60.0),
+            (Rgb::green(), 120.0),
+            (Rgb::cyan(), 180.0),
+            (Rgb::blue(), 240.0),
+            (Rgb::magenta(), 300.0),
+        ];
+
+        for (rgb, hue) in corners {
+            let Hsv(h, s, v) = Hsv::from(rgb);
+            assert!((h - hue).abs() < 0.001, "{:?} {}", rgb, h);
+            assert!((s - 1.0).abs() < 0.001, "{:?} {}", rgb, s);
+            assert!((v - 1.0).abs() < 0.001, "{:?} {}", rgb, v);
+
+            assert_rgb_eq(Rgb::from(Hsv(hue, 1.0, 1.0)), rgb, 0.001);
+        }
+    }
+
+    #[test]
+    fn hsv_of_grays() {
+        let Hsv(h, s, v) = Hsv::from(Rgb::black());
+        assert_eq!([h, s, v], [0.0, 0.0, 0.0]);
+
+        let Hsv(h, s, v) = Hsv::from(Rgb(0.5, 0.5, 0.5));
+        assert_eq!([h, s, v], [0.0, 0.0, 0.5]);
+    }
+
+    #[test]
+    fn gradient_reversed() {
+        let gradient = Gradient::with_stops([
+            (0.0, Rgb::black()),
+            (0.1, Rgb::red()),
+            (0.7, Rgb::yellow()),
+            (1.0, Rgb::white()),
+        ])
+        .unwrap();
+        let reversed = gradient.reversed();
+
+        for x in [0.0, 0.05, 0.2, 0.3, 0.5, 0.85, 0.95, 1.0] {
+            assert_rgb_eq(reversed.sample(x), gradient.sample(1.0 - x), 0.000_1);
+        }
+
+        assert_rgb_eq(reversed.sample(0.2), gradient.sample(0.8), 0.000_1);
+        assert_rgb_eq(reversed.sample(0.0), Rgb::white(), 0.001);
+        assert_rgb_eq(reversed.sample(1.0), Rgb::black(), 0.001);
+    }
+
+    #[test]
+    fn sample_into_matches_sample() {
+        let gradient = Gradient::with_stops([
+            (0.0, OkLab::from(Rgb::black())),
+            (0.1, OkLab::from(Rgb::red())),
+            (0.5, OkLab::from(Rgb::cyan())),
+            (0.7, OkLab::from(Rgb::yellow())),
+            (1.0, OkLab::from(Rgb::white())),
+        ])
+        .unwrap();
+
+        // sorted runs, jumps in both directions, stop positions and values outside of [0, 1]
+        let mut heights = (-20..=120).map(|x| x as f32 / 100.0).collect::<Vec<_>>();
+        heights.extend([
+            0.5,
+            0.1,
+            0.9,
+            0.0,
+            1.0,
+            0.7,
+            0.69,
+            0.71,
+            -2.5,
+            2.5,
+            0.3,
+            f32::NAN,
+        ]);
+        let mut rng = crate::rng::Rng::new(crate::rng::Seed(7));
+        heights.extend((0..200).map(|_| rng.next_f32_between(-1.0, 2.0)));
+
+        let spaces = [
+            GradientSpace::Rgb,
+            GradientSpace::OkLab,
+            GradientSpace::OkLch,
+        ];
+        let edges = [
+            GradientEdge::Clamp,
+            GradientEdge::Wrap,
+            GradientEdge::Mirror,
+        ];
+
+        for space in spaces {
+            for edge in edges {
+                let gradient = gradient.clone().with_space(space).with_edge(edge);
+
+                let mut colors = vec![OkLab::default(); heights.len()];
+                gradient.sample_into(&heights, &mut colors);
+
+                for (&h, &color) in heights.iter().zip(colors.iter()) {
+                    let expected = gradient.sample(h).to_f32();
+                    let actual = color.to_f32();
+                    assert_eq!(actual, expected, "{:?} {:?} {}", space, edge, h);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn distance() {
+        let colors = [Rgb::black(), Rgb::white(), Rgb::red(), Rgb(0.2, 0.4, 0.6)];
+        for color in colors {
+            assert_eq!(color.distance(color), 0.0);
+            assert_eq!(OkLab::from(color).distance(OkLab::from(color)), 0.0);
+        }
+
+        let black = Rgb::black();
+        let dark_gray = Rgb(0.2, 0.2, 0.2);
+        let white = Rgb::white();
+        assert!(black.distance(white) > black.distance(dark_gray));
+        assert!(black.distance(dark_gray) > 0.0);
+
+        // symmetric
+        assert_eq!(black.distance(white), white.distance(black));
+    }
+
+    #[test]
+    fn hex_round_trip() {
+        let rgb = [
+            [0, 0, 0],
+            [255, 255, 255],
+            [0x12, 0xab, 0x9c],
+            [1, 128, 254],
+        ];
+        for bytes in rgb {
+            let hex = Rgb::from_u8(bytes).to_hex();
+            assert_eq!(hex.len(), 7);
+            assert_eq!(hex, hex.to_lowercase());
+            assert_eq!(Rgb::from_hex(&hex).unwrap().to_u8(), bytes, "{}", hex);
+        }
+
+        assert_eq!(Rgb::from_u8([0x12, 0xab, 0x9c]).to_hex(), "#12ab9c");
+
+        // every byte value, in every channel
+        for i in 0..=255u8 {
+            let bytes = [i, i.wrapping_add(85), i.wrapping_add(170), 255 - i];
+            let hex = Rgba::from_u8(bytes).to_hex();
+            assert_eq!(hex.len(), 9);
+            assert_eq!(Rgba::from_hex(&hex).unwrap().to_u8(), bytes, "{}", hex);
+        }
+    }
+
+    #[test]
+    fn oklab_mix() {
+        let a = OkLab(0.2, -0.1, 0.3);
+        let b = OkLab(0.8, 0.1, -0.1);
+
+        assert_eq!(OkLab::mix(a, b, 0.0).to_f32(), a.to_f32());
+        assert_eq!(OkLab::mix(a, b, 1.0).to_f32(), b.to_f32());
+
+        let OkLab(l, a_, b_) = OkLab::mix(a, b, 0.5);
+        assert!((l - 0.5).abs() < 0.000_01, "{}", l);
+        assert!(a_.abs() < 0.000_01, "{}", a_);
+        assert!((b_ - 0.1).abs() < 0.000_01, "{}", b_);
+
+        // t is clamped
+        assert_eq!(OkLab::mix(a, b, -1.0).to_f32(), a.to_f32());
+        assert_eq!(OkLab::mix(a, b, 2.0).to_f32(), b.to_f32());
+    }
+
+    #[test]
+    fn quantize_evenly_spaced_stops() {
+        let gradient = Gradient::try_from([Rgb::black(), Rgb::white()]).unwrap();
+        assert_eq!(gradient.quantize(0.49).to_f32(), Rgb::black().to_f32());
+        assert_eq!(gradient.quantize(0.51).to_f32(), Rgb::white().to_f32());
+
+        let gradient = Gradient::try_from([Rgb::black(), Rgb::red(), Rgb::white()]).unwrap();
+        assert_eq!(gradient.quantize(0.24).to_f32(), Rgb::black().to_f32());
+        assert_eq!(gradient.quantize(0.26).to_f32(), Rgb::red().to_f32());
+        assert_eq!(gradient.quantize(0.74).to_f32(), Rgb::red().to_f32());
+        assert_eq!(gradient.quantize(0.76).to_f32(), Rgb::white().to_f32());
+    }
+
+    #[test]
+    fn contrast_ratio() {
+        let ratio = Rgb::white().contrast_ratio(Rgb::black());
+        assert!((ratio - 21.0).abs() < 0.001, "{}", ratio);
+        let ratio = Rgb::black().contrast_ratio(Rgb::white());
+        assert!((ratio - 21.0).abs() < 0.001, "{}", ratio);
+
+        for color in [Rgb::black(), Rgb::white(), Rgb::red(), Rgb(0.2, 0.4, 0.6)] {
+            let ratio = color.contrast_ratio(color);
+            assert!((ratio - 1.0).abs() < 0.000_01, "{:?} {}", color, ratio);
+        }
+    }
+
+    #[test]
+    fn blend_modes() {
+        // the values are linear light, because that's where the blending happens
+        let pairs = [
+            (
+                [0.2, 0.6, 0.8],
+                [0.5, 0.25, 1.0],
+                [
+                    (BlendMode::Normal, [0.5, 0.25, 1.0]),
+                    (BlendMode::Multiply, [0.1, 0.15, 0.8]),
+                    (BlendMode::Screen, [0.6, 0.7, 1.0]),
+                    (BlendMode::Overlay, [0.2, 0.4, 1.0]),
+                ],
+            ),
+            (
+                [1.0, 0.0, 0.4],
+                [0.5, 0.5, 0.5],
+                [
+                    (BlendMode::Normal, [0.5, 0.5, 0.5]),
+                    (BlendMode::Multiply, [0.5, 0.0, 0.2]),
+                    (BlendMode::Screen, [1.0, 0.5, 0.7]),
+                    (BlendMode::Overlay, [1.0, 0.0, 0.4]),
+                ],
+            ),
+        ];
+
+        for (below, over, expected) in pairs {
+            let below = Rgb::from_linear(Rgb::from_f32(below));
+            let over = Rgb::from_linear(Rgb::from_f32(over));
+            for (mode, expected) in expected {
+                let actual = below.blend(over, mode).to_linear();
+                assert_rgb_eq(actual, Rgb::from_f32(expected), 0.001);
+            }
+        }
+    }
+
+    #[test]
+    fn to_u8_gamma_of_one_equals_to_u8() {
+        let colors = [
+            Rgb::black(),
+            Rgb::white(),
+            Rgb(0.1, 0.5, 0.9),
+            Rgb(0.333, 0.666, 0.999),
+            Rgb(-0.5, 1.5, 0.25),
+        ];
+
+        for color in colors {
+            assert_eq!(color.to_u8_gamma(1.0), color.to_u8(), "{:?}", color);
+        }
+
+        for i in 0..=255 {
+            let color = Rgb::from_u8([i, 255 - i, i / 2]);
+            assert_eq!(color.to_u8_gamma(1.0), color.to_u8(), "{:?}", color);
+        }
+    }
+
+    #[test]
+    fn duplicate_position_names_the_stop() {
+        let result = Gradient::with_stops([
+            (0.0, Rgb::black()),
+            (0.3, Rgb::red()),
+            (0.6, Rgb::green()),
+            (0.6, Rgb::blue()),
+            (1.0, Rgb::white()),
+        ]);
+
+        let error = result.unwrap_err();
+        assert_eq!(error.index, 3);
+        assert_eq!(error.reason, InvalidStopReason::DuplicatePosition);
     }
 }
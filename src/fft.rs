@@ -0,0 +1,113 @@
+#![allow(unused)]
+
+// iterative radix-2 cooley-tukey fft: https://en.wikipedia.org/wiki/Cooley%E2%80%93Tukey_FFT_algorithm
+
+use std::f32::consts::PI;
+
+#[derive(Debug)]
+pub struct NotAPowerOfTwo(pub usize);
+
+impl std::fmt::Display for NotAPowerOfTwo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "length must be a power of two, but was {}", self.0)
+    }
+}
+
+impl std::error::Error for NotAPowerOfTwo {}
+
+/// transforms `re` and `im` in place. `re` and `im` are the real and imaginary parts of the signal
+/// and must be of the same length, which must be a power of two. if `inverse` is true, the inverse
+/// transform is computed, including the division by the length.
+pub fn fft(re: &mut [f32], im: &mut [f32], inverse: bool) -> Result<(), NotAPowerOfTwo> {
+    let n = re.len();
+    if !n.is_power_of_two() || im.len() != n {
+        return Err(NotAPowerOfTwo(n));
+    }
+
+    // bit reversal permutation
+    let bits = n.trailing_zeros();
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if bits > 0 && i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    // butterflies
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let mut len = 2;
+    while len <= n {
+        let angle = sign * 2.0 * PI / len as f32;
+        let half = len / 2;
+
+        for start in (0..n).step_by(len) {
+            for k in 0..half {
+                let w_re = f32::cos(angle * k as f32);
+                let w_im = f32::sin(angle * k as f32);
+
+                let a = start + k;
+                let b = a + half;
+
+                let t_re = re[b] * w_re - im[b] * w_im;
+                let t_im = re[b] * w_im + im[b] * w_re;
+
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+
+        len <<= 1;
+    }
+
+    if inverse {
+        let scale = 1.0 / n as f32;
+        for i in 0..n {
+            re[i] *= scale;
+            im[i] *= scale;
+        }
+    }
+
+    Ok(())
+}
+
+/// transforms a square, row major `width * width` signal in place, by transforming all rows and
+/// then all columns. `width` must be a power of two.
+pub fn fft_2d(
+    re: &mut [f32],
+    im: &mut [f32],
+    width: usize,
+    inverse: bool,
+) -> Result<(), NotAPowerOfTwo> {
+    if !width.is_power_of_two() || re.len() != width * width || im.len() != width * width {
+        return Err(NotAPowerOfTwo(width));
+    }
+
+    // rows
+    for iy in 0..width {
+        let start = iy * width;
+        let end = start + width;
+        fft(&mut re[start..end], &mut im[start..end], inverse)?;
+    }
+
+    // columns
+    let mut column_re = vec![0.0; width];
+    let mut column_im = vec![0.0; width];
+    for ix in 0..width {
+        for iy in 0..width {
+            column_re[iy] = re[iy * width + ix];
+            column_im[iy] = im[iy * width + ix];
+        }
+
+        fft(&mut column_re, &mut column_im, inverse)?;
+
+        for iy in 0..width {
+            re[iy * width + ix] = column_re[iy];
+            im[iy * width + ix] = column_im[iy];
+        }
+    }
+
+    Ok(())
+}
@@ -1,5 +1,6 @@
 #![allow(unused)]
 
+use std::io::Cursor;
 use std::io::Error;
 use std::io::ErrorKind;
 use std::io::Read;
@@ -7,6 +8,11 @@ use std::io::Result;
 use std::io::Seek;
 use std::io::SeekFrom;
 use std::io::Write;
+use std::path::Path;
+
+use crate::terrain_generator::HeightMap;
+use crate::terrain_generator::Side;
+use crate::vector::Vec3;
 
 pub const ADDR_SIZE: usize = std::mem::size_of::<u64>();
 
@@ -69,6 +75,12 @@ pub fn seek(stream: &mut impl Seek, pos: SeekFrom) -> Result<u64> {
 // write
 //
 
+// numbers are written and read in little endian, unless the name of the function says otherwise.
+//
+// each function writes directly to the stream. when writing many small values to a file, wrap it
+// in a `std::io::BufWriter`, which implements `Seek` too, or write into a `std::io::Cursor` first
+// and write the whole buffer at once. otherwise every value results in its own syscall.
+
 /// writes and advances the stream. if not all bytes were written, an error is returned. returns a
 /// `FatPtr` to the bytes written.
 pub fn write(stream: &mut (impl Write + Seek), buf: &[u8]) -> Result<FatPtr> {
@@ -139,12 +151,25 @@ pub fn write_i32(stream: &mut (impl Write + Seek), value: i32) -> Result<FatPtr>
     write(stream, &bytes)
 }
 
-/// writes a `f64` and advances the stream. returns a `FatPtr` to the byte written.
+/// writes an `f32` in little endian and advances the stream. same as `write_f32_le`. returns a
+/// `FatPtr` to the byte written.
 pub fn write_f32(stream: &mut (impl Write + Seek), value: f32) -> Result<FatPtr> {
+    write_f32_le(stream, value)
+}
+
+/// writes an `f32` in little endian and advances the stream. returns a `FatPtr` to the byte
+/// written.
+pub fn write_f32_le(stream: &mut (impl Write + Seek), value: f32) -> Result<FatPtr> {
     let bytes = value.to_le_bytes();
     write(stream, &bytes)
 }
 
+/// writes an `f32` in big endian and advances the stream. returns a `FatPtr` to the byte written.
+pub fn write_f32_be(stream: &mut (impl Write + Seek), value: f32) -> Result<FatPtr> {
+    let bytes = value.to_be_bytes();
+    write(stream, &bytes)
+}
+
 /// writes an `1` if `value` is `true`, `0` otherwise. it advances the stream. returns a `FatPtr` to the byte written.
 pub fn write_bool(stream: &mut (impl Write + Seek), value: bool) -> Result<FatPtr> {
     match value {
@@ -279,14 +304,50 @@ pub fn read_i32(stream: &mut impl Read) -> Result<i32> {
     Ok(i32::from_le_bytes(bytes))
 }
 
-/// reads an `f32` and advances the stream.
+/// reads an `f32` in little endian and advances the stream. same as `read_f32_le`.
 pub fn read_f32(stream: &mut impl Read) -> Result<f32> {
+    read_f32_le(stream)
+}
+
+/// reads an `f32` in little endian and advances the stream.
+pub fn read_f32_le(stream: &mut impl Read) -> Result<f32> {
     let mut bytes = [0; 4];
     read(stream, &mut bytes)?;
 
     Ok(f32::from_le_bytes(bytes))
 }
 
+/// reads an `f32` in big endian and advances the stream.
+pub fn read_f32_be(stream: &mut impl Read) -> Result<f32> {
+    let mut bytes = [0; 4];
+    read(stream, &mut bytes)?;
+
+    Ok(f32::from_be_bytes(bytes))
+}
+
+/// reads `count` `f32`s in little endian, like `read_f32` does, and advances the stream. returns an
+/// error of kind `InvalidData` if `count` is too large to be addressed in bytes, and
+/// `UnexpectedEof` if the stream ends before `count` values were read.
+pub fn read_f32s(stream: &mut impl Read, count: usize) -> Result<Vec<f32>> {
+    let len = count
+        .checked_mul(4)
+        .ok_or_else(|| Error::from(ErrorKind::InvalidData))?;
+
+    // the buffer grows with the bytes that are actually read, such that a corrupt count cannot
+    // request absurd amounts of memory
+    let mut bytes = Vec::new();
+    stream.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len {
+        return Err(Error::from(ErrorKind::UnexpectedEof));
+    }
+
+    let values = bytes
+        .chunks_exact(4)
+        .map(|x| f32::from_le_bytes([x[0], x[1], x[2], x[3]]))
+        .collect();
+    Ok(values)
+}
+
 /// reads an `u8` and advances the stream. returns `true` the read value is `1`, `false` if the read value is `0`, and an
 /// error otherwise.
 pub fn read_bool(stream: &mut impl Read) -> Result<bool> {
@@ -316,3 +377,384 @@ pub fn read_string(stream: &mut (impl Read + Seek)) -> Result<String> {
     let string = String::from_utf8(bytes).map_err(|_| Error::from(ErrorKind::InvalidData))?;
     Ok(string)
 }
+
+//
+// tar
+//
+
+pub const TAR_BLOCK_SIZE: usize = 512;
+
+/// writes a single file into a tar archive (ustar format) and advances the stream. only regular
+/// files are supported, and the data is stored as is, without compression. `name` must not be
+/// longer than 100 bytes. returns a `FatPtr` to the header and the data written, including
+/// padding.
+pub fn write_tar_entry(
+    stream: &mut (impl Write + Seek),
+    name: impl AsRef<str>,
+    bytes: &[u8],
+) -> Result<FatPtr> {
+    let name = name.as_ref().as_bytes();
+    if name.len() > 100 {
+        return Err(Error::from(ErrorKind::InvalidInput));
+    }
+
+    // numbers are stored as zero terminated octal strings
+    fn octal(field: &mut [u8], value: u64) {
+        let digits = field.len() - 1;
+        let string = format!("{:0width$o}", value, width = digits);
+        field[..digits].copy_from_slice(&string.as_bytes()[string.len() - digits..]);
+    }
+
+    if bytes.len() as u64 >= 1 << 33 {
+        // does not fit into 11 octal digits
+        return Err(Error::from(ErrorKind::InvalidInput));
+    }
+
+    let mut header = [0u8; TAR_BLOCK_SIZE];
+    header[..name.len()].copy_from_slice(name);
+    octal(&mut header[100..108], 0o644); // mode
+    octal(&mut header[108..116], 0); // uid
+    octal(&mut header[116..124], 0); // gid
+    octal(&mut header[124..136], bytes.len() as u64); // size
+    octal(&mut header[136..148], 0); // mtime
+    header[156] = b'0'; // typeflag: regular file
+    header[257..263].copy_from_slice(b"ustar\0"); // magic
+    header[263..265].copy_from_slice(b"00"); // version
+
+    // the checksum is calculated while the checksum field is filled with spaces
+    header[148..156].fill(b' ');
+    let checksum = header.iter().map(|&b| b as u64).sum::<u64>();
+    octal(&mut header[148..155], checksum);
+
+    let begin = seek(stream, SeekFrom::Current(0))?;
+    write(stream, &header)?;
+    write(stream, bytes)?;
+
+    let padding = (TAR_BLOCK_SIZE - bytes.len() % TAR_BLOCK_SIZE) % TAR_BLOCK_SIZE;
+    let fat_ptr = write(stream, &vec![0; padding])?;
+
+    FatPtr::begin_end(begin, fat_ptr.end())
+}
+
+/// writes the end of a tar archive, which consists of two empty blocks, and advances the stream.
+/// returns a `FatPtr` to the bytes written.
+pub fn write_tar_end(stream: &mut (impl Write + Seek)) -> Result<FatPtr> {
+    write(stream, &[0; 2 * TAR_BLOCK_SIZE])
+}
+
+//
+// ply
+//
+
+/// writes a point cloud as a binary little endian ply and advances the stream. returns a `FatPtr`
+/// to the bytes written.
+pub fn write_ply_points(stream: &mut (impl Write + Seek), points: &[Vec3]) -> Result<FatPtr> {
+    let begin = seek(stream, SeekFrom::Current(0))?;
+
+    let mut header = String::from("ply\nformat binary_little_endian 1.0\n");
+    header.push_str(&format!("element vertex {}\n", points.len()));
+    header.push_str("property float x\nproperty float y\nproperty float z\n");
+    header.push_str("end_header\n");
+    let mut fat_ptr = write(stream, header.as_bytes())?;
+
+    for &Vec3(x, y, z) in points.iter() {
+        write_f32(stream, x)?;
+        write_f32(stream, y)?;
+        fat_ptr = write_f32(stream, z)?;
+    }
+
+    FatPtr::begin_end(begin, fat_ptr.end())
+}
+
+/// writes a triangle mesh as a binary little endian ply and advances the stream. `normals` may be
+/// empty, in which case no normals are written. otherwise it must contain one normal per vertex.
+/// returns a `FatPtr` to the bytes written.
+pub fn write_ply_mesh(
+    stream: &mut (impl Write + Seek),
+    vertices: &[Vec3],
+    normals: &[Vec3],
+    indices: &[[u32; 3]],
+) -> Result<FatPtr> {
+    let has_normals = !normals.is_empty();
+    if has_normals && normals.len() != vertices.len() {
+        return Err(Error::from(ErrorKind::InvalidInput));
+    }
+
+    let begin = seek(stream, SeekFrom::Current(0))?;
+
+    let mut header = String::from("ply\nformat binary_little_endian 1.0\n");
+    header.push_str(&format!("element vertex {}\n", vertices.len()));
+    header.push_str("property float x\nproperty float y\nproperty float z\n");
+    if has_normals {
+        header.push_str("property float nx\nproperty float ny\nproperty float nz\n");
+    }
+    header.push_str(&format!("element face {}\n", indices.len()));
+    header.push_str("property list uchar uint vertex_indices\n");
+    header.push_str("end_header\n");
+    let mut fat_ptr = write(stream, header.as_bytes())?;
+
+    for (i, &Vec3(x, y, z)) in vertices.iter().enumerate() {
+        write_f32(stream, x)?;
+        write_f32(stream, y)?;
+        fat_ptr = write_f32(stream, z)?;
+
+        if has_normals {
+            let Vec3(nx, ny, nz) = normals[i];
+            write_f32(stream, nx)?;
+            write_f32(stream, ny)?;
+            fat_ptr = write_f32(stream, nz)?;
+        }
+    }
+
+    for &[a, b, c] in indices.iter() {
+        write_u8(stream, 3)?;
+        write_u32(stream, a)?;
+        write_u32(stream, b)?;
+        fat_ptr = write_u32(stream, c)?;
+    }
+
+    FatPtr::begin_end(begin, fat_ptr.end())
+}
+
+//
+// height map
+//
+
+pub const HEIGHT_MAP_MAGIC: [u8; 4] = *b"rthm";
+pub const HEIGHT_MAP_VERSION: u32 = 1;
+
+/// writes a height map to `path`, such that it can be read without knowing its width or side.
+/// missing directories are created, and an existing file is replaced.
+///
+/// the file consists of `HEIGHT_MAP_MAGIC`, `HEIGHT_MAP_VERSION` as `u32`, the width as `u32`, the
+/// index of the side as `u8`, followed by `width * width` `f32`s in little endian.
+pub fn write_heightmap(path: impl AsRef<Path>, height_map: &HeightMap, width: usize) -> Result<()> {
+    let path = path.as_ref();
+
+    if height_map.values.len() != width * width {
+        return Err(Error::from(ErrorKind::InvalidInput));
+    }
+    let width = u32::try_from(width).map_err(|_| Error::from(ErrorKind::InvalidInput))?;
+
+    let mut stream = Cursor::new(Vec::new());
+    write(&mut stream, &HEIGHT_MAP_MAGIC)?;
+    write_u32(&mut stream, HEIGHT_MAP_VERSION)?;
+    write_u32(&mut stream, width)?;
+    write_u8(&mut stream, height_map.side.to_index() as u8)?;
+    for &value in height_map.values.iter() {
+        write_f32_le(&mut stream, value)?;
+    }
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+
+    let mut file = std::fs::File::create_new(path)?;
+    write(&mut file, stream.get_ref())?;
+
+    Ok(())
+}
+
+/// reads a height map, which was written by `write_heightmap`. returns the height map and its
+/// width. returns an error of kind `InvalidData` if the header is invalid or if there is data
+/// after the values, and `UnexpectedEof` if the file is truncated.
+pub fn read_heightmap(path: impl AsRef<Path>) -> Result<(HeightMap, usize)> {
+    let bytes = std::fs::read(path)?;
+    let mut stream = Cursor::new(bytes.as_slice());
+
+    let mut magic = [0; 4];
+    read(&mut stream, &mut magic)?;
+    if magic != HEIGHT_MAP_MAGIC {
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+
+    let version = read_u32(&mut stream)?;
+    if version != HEIGHT_MAP_VERSION {
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+
+    let width = read_u32(&mut stream)? as usize;
+    let side = read_u8(&mut stream)? as usize;
+    if side >= 6 {
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+
+    // check the length before allocating, such that a corrupt width cannot request absurd amounts
+    // of memory
+    let remaining = bytes.len() - stream.position() as usize;
+    let expected = width
+        .checked_mul(width)
+        .and_then(|x| x.checked_mul(4))
+        .ok_or_else(|| Error::from(ErrorKind::InvalidData))?;
+    if remaining < expected {
+        return Err(Error::from(ErrorKind::UnexpectedEof));
+    }
+    if remaining > expected {
+        return Err(Error::from(ErrorKind::InvalidData));
+    }
+
+    let values = read_f32s(&mut stream, width * width)?;
+    let height_map = HeightMap {
+        values,
+        side: Side::from(side),
+    };
+
+    Ok((height_map, width))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir()
+            .join(format!("ris_terrain_generator_{}", std::process::id()))
+            .join(name)
+    }
+
+    #[test]
+    fn heightmap_round_trip() {
+        let width = 4;
+        let height_map = HeightMap {
+            values: (0..width * width).map(|x| x as f32 * 0.25 - 1.0).collect(),
+            side: Side::R,
+        };
+
+        let path = temp_path("round_trip.rthm");
+        write_heightmap(&path, &height_map, width).unwrap();
+        let (read_map, read_width) = read_heightmap(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(read_width, width);
+        assert_eq!(read_map.side, height_map.side);
+        assert_eq!(read_map.values, height_map.values);
+    }
+
+    #[test]
+    fn heightmap_truncated_is_unexpected_eof() {
+        let width = 4;
+        let height_map = HeightMap {
+            values: vec![0.5; width * width],
+            side: Side::U,
+        };
+
+        let path = temp_path("truncated.rthm");
+        write_heightmap(&path, &height_map, width).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &bytes[..bytes.len() - 3]).unwrap();
+        let error = read_heightmap(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn read_f32s_overflowing_count_is_invalid_data() {
+        let mut stream = Cursor::new(vec![0u8; 8]);
+        let error = read_f32s(&mut stream, usize::MAX).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn read_f32s_round_trip() {
+        let values = [0.0, -0.0, 1.0, -1.5, 0.1, f32::MIN, f32::MAX, f32::EPSILON];
+
+        let mut stream = Cursor::new(Vec::new());
+        for &value in values.iter() {
+            write_f32(&mut stream, value).unwrap();
+        }
+
+        stream.set_position(0);
+        let read_values = read_f32s(&mut stream, values.len()).unwrap();
+        assert_eq!(read_values, values);
+        assert_eq!(stream.position(), 4 * values.len() as u64);
+    }
+
+    #[test]
+    fn read_f32s_huge_count_is_unexpected_eof() {
+        // must fail on the missing bytes, instead of trying to allocate them upfront
+        for count in [3, usize::MAX / 8, usize::MAX / 4] {
+            let mut stream = Cursor::new(vec![0u8; 8]);
+            let error = read_f32s(&mut stream, count).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+        }
+    }
+
+    #[test]
+    fn f32_byte_order() {
+        let le = [0x00, 0x00, 0x80, 0x3f];
+        let be = [0x3f, 0x80, 0x00, 0x00];
+
+        let mut stream = Cursor::new(Vec::new());
+        write_f32_le(&mut stream, 1.0).unwrap();
+        assert_eq!(stream.get_ref().as_slice(), le);
+
+        let mut stream = Cursor::new(Vec::new());
+        write_f32_be(&mut stream, 1.0).unwrap();
+        assert_eq!(stream.get_ref().as_slice(), be);
+
+        let mut stream = Cursor::new(Vec::new());
+        write_f32(&mut stream, 1.0).unwrap();
+        assert_eq!(stream.get_ref().as_slice(), le);
+
+        assert_eq!(read_f32_le(&mut Cursor::new(le)).unwrap(), 1.0);
+        assert_eq!(read_f32_be(&mut Cursor::new(be)).unwrap(), 1.0);
+        assert_eq!(read_f32(&mut Cursor::new(le)).unwrap(), 1.0);
+
+        // reading in the wrong order does not return the same value
+        assert_ne!(read_f32_le(&mut Cursor::new(be)).unwrap(), 1.0);
+    }
+
+    #[test]
+    fn buffered_writes_match_direct_writes() {
+        fn write_all(stream: &mut (impl Write + Seek)) -> Result<FatPtr> {
+            let begin = write_u8(stream, 7)?;
+            write_u16(stream, 0x1234)?;
+            write_u32(stream, 0xdead_beef)?;
+            write_f32_le(stream, 1.5)?;
+            write_f32_be(stream, -2.25)?;
+            write_bool(stream, true)?;
+            write_fat_ptr(stream, FatPtr { addr: 3, len: 5 })?;
+            let end = write_string(stream, "height map")?;
+            FatPtr::begin_end(begin.addr, end.end())
+        }
+
+        let mut direct = Cursor::new(Vec::new());
+        let direct_ptr = write_all(&mut direct).unwrap();
+
+        let mut buffered = std::io::BufWriter::new(Cursor::new(Vec::new()));
+        let buffered_ptr = write_all(&mut buffered).unwrap();
+        let buffered = buffered.into_inner().unwrap();
+
+        assert_eq!(buffered_ptr, direct_ptr);
+        assert_eq!(buffered.get_ref(), direct.get_ref());
+    }
+
+    #[test]
+    fn u16_u32_round_trip() {
+        let mut stream = Cursor::new(Vec::new());
+        for value in [0, 1, 0x1234, u16::MAX] {
+            write_u16(&mut stream, value).unwrap();
+        }
+        for value in [0, 1, 0x1234_5678, u32::MAX] {
+            write_u32(&mut stream, value).unwrap();
+        }
+        assert_eq!(stream.get_ref().len(), 4 * 2 + 4 * 4);
+
+        stream.set_position(0);
+        for value in [0, 1, 0x1234, u16::MAX] {
+            assert_eq!(read_u16(&mut stream).unwrap(), value);
+        }
+        for value in [0, 1, 0x1234_5678, u32::MAX] {
+            assert_eq!(read_u32(&mut stream).unwrap(), value);
+        }
+
+        // little endian
+        assert_eq!(stream.get_ref()[4..6], [0x34, 0x12]);
+        assert_eq!(stream.get_ref()[16..20], [0x78, 0x56, 0x34, 0x12]);
+    }
+}
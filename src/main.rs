@@ -1,7 +1,9 @@
 mod color;
 mod common;
+mod fft;
 mod io;
 mod matrix;
+mod mesh;
 mod pcg;
 mod qoi;
 mod quaternion;
@@ -10,8 +12,10 @@ mod terrain_generator;
 mod util;
 mod vector;
 
+use std::path::Path;
 use std::path::PathBuf;
 
+use crate::color::named;
 use crate::color::ByteColor;
 use crate::color::Gradient;
 use crate::color::OkLab;
@@ -21,22 +25,42 @@ use crate::qoi::ColorSpace;
 use crate::qoi::QoiDesc;
 use crate::rng::Seed;
 use crate::terrain_generator::Args;
+use crate::terrain_generator::BoundaryFalloff;
+use crate::terrain_generator::BoundaryStyle;
 use crate::terrain_generator::ErosionKind;
 use crate::terrain_generator::HeightMap;
+use crate::terrain_generator::NoiseKind;
 use crate::terrain_generator::Side;
+use crate::vector::Vec2;
 
 fn main() {
     // settings
     let seed = Seed::new();
     let width = 1 << 8;
     let preview_width = 1 << 8; // for the preview to be useful, keep this quite small
+    let flow_arrows = false; // additionally writes a preview with downhill arrows
+    let flow_arrow_spacing = 16;
+    let sea_level = 0.25;
+    let coastline_supersamples = 1; // off. e.g. 4 antialiases the coastline with 4² samples
+    let bundle = false; // additionally writes all bins, qois and a manifest into a single tar
+    let output = OutputConfig {
+        dir: PathBuf::from("."),
+        stem: String::from("height_map"),
+    };
 
     let args = Args {
         seed,
         width,
         continent_count: 6,
+        continent_growth_bias: 0.0,
+        continent_min_speed: 1.0,
+        continent_max_speed: 1.0,
         continental_mountain_thickness: width / 2,
+        boundary_style: BoundaryStyle::Sharp,
+        boundary_falloff: BoundaryFalloff::Quadratic,
+        noise_kind: NoiseKind::Perlin,
         fractal_main_layer: 2,
+        spectral_beta: 2.0,
         fractal_weight: 0.25,
         erosion_kind: ErosionKind::Rng,
         erosion_iterations: width * width * 6,
@@ -51,80 +75,139 @@ fn main() {
         erosion_deposit_speed: 0.004,
         erosion_gravity: 8.0,
         erosion_evaporate_speed: 0.01,
+        glacier_iterations: 0,
+        glacier_freezing_temperature: 0.25,
+        glacier_lapse_rate: 0.5,
+        glacier_accumulation: 0.001,
+        glacier_flow_rate: 0.1,
+        glacier_erode_speed: 0.05,
+        sea_level,
+        island_arc_density: 0.0,
+        island_arc_offset: width / 16,
+        median_filter_radius: 0,
+        unsharp_mask_sigma: 2.0,
+        unsharp_mask_amount: 0.0,
+        keep_layers: false,
+        reject_degenerate: false,
+        high_precision: false,
     };
 
     // run terrain generator
-    let result = terrain_generator::run(args);
+    let result = match terrain_generator::run(args) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("failed to generate terrain: {}", e);
+            return;
+        }
+    };
 
     // use heightmap as desired
-    if let Err(e) = save_as_bin(&result) {
+    if let Err(e) = save_as_bin(&output, &result) {
         eprintln!("failed to save bin: {}", e);
     }
 
-    if let Err(e) = save_as_qoi(width, &result) {
+    let coloring = Coloring {
+        sea_level,
+        coastline_supersamples,
+    };
+
+    if let Err(e) = save_as_qoi(&output, width, coloring, &result) {
         eprintln!("failed to save qoi: {}", e);
     }
 
-    if let Err(e) = save_as_qoi_preview(width, preview_width, &result) {
+    if let Err(e) = save_as_qoi_preview(&output, width, preview_width, coloring, &result) {
         eprintln!("failed to save preview: {}", e);
     }
 
+    if flow_arrows {
+        if let Err(e) = save_as_qoi_flow_arrows(&output, width, flow_arrow_spacing, &result) {
+            eprintln!("failed to save flow arrows: {}", e);
+        }
+    }
+
+    if bundle {
+        let path = output.path("bundle.tar");
+        if let Err(e) = save_bundle(path, &output, width, preview_width, coloring, &result) {
+            eprintln!("failed to save bundle: {}", e);
+        }
+    }
+
     eprintln!("done! seed: {:?}", seed);
 }
 
 fn save_as_bin<'a>(
+    output: &OutputConfig,
     height_maps: impl IntoIterator<Item = &'a HeightMap>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     for (i, height_map) in height_maps.into_iter().enumerate() {
-        let HeightMap { values, side } = height_map;
         eprintln!("serializing bin... {}/6", i + 1);
+        let bytes = encode_bin(height_map)?;
+        save_file(output.side_path(height_map.side, "bin"), bytes)?;
+    }
 
-        let data_len = values.len() * 4;
-        let mut data = std::io::Cursor::new(Vec::with_capacity(data_len));
+    Ok(())
+}
 
-        for v in values {
-            crate::io::write_f32(&mut data, *v)?;
-        }
+fn encode_bin(height_map: &HeightMap) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let values = &height_map.values;
+    let data_len = values.len() * 4;
+    let mut data = std::io::Cursor::new(Vec::with_capacity(data_len));
 
-        let bytes = data.into_inner();
-        save_file(format!("height_map_{}.bin", side), bytes)?;
+    for v in values {
+        crate::io::write_f32_le(&mut data, *v)?;
     }
 
-    Ok(())
+    Ok(data.into_inner())
 }
 
-fn save_as_qoi<'a>(
+fn save_as_qoi(
+    output: &OutputConfig,
     width: usize,
-    height_maps: impl IntoIterator<Item = &'a crate::terrain_generator::HeightMap>,
+    coloring: Coloring,
+    height_maps: &[HeightMap],
 ) -> Result<(), Box<dyn std::error::Error>> {
     let gradient = colored_height_gradient()?;
 
-    for (i, height_map) in height_maps.into_iter().enumerate() {
-        let HeightMap { values, side } = height_map;
+    for (i, height_map) in height_maps.iter().enumerate() {
         eprintln!("serializing qoi... {}/6", i + 1);
+        let qoi_bytes = encode_qoi(width, coloring, &gradient, height_map, height_maps)?;
+        save_file(output.side_path(height_map.side, "qoi"), qoi_bytes)?;
+    }
 
-        let mut bytes = Vec::with_capacity(values.len() * 3);
-
-        for &h in values.iter() {
-            let lab = gradient.sample(h);
-            let rgb = Rgb::from(lab);
-            let [r, g, b] = rgb.to_u8();
-            bytes.push(r);
-            bytes.push(g);
-            bytes.push(b);
-        }
+    Ok(())
+}
 
-        let desc = QoiDesc {
-            width: width as u32,
-            height: width as u32,
-            channels: Channels::RGB,
-            color_space: ColorSpace::SRGB,
-        };
-        let qoi_bytes = qoi::encode(&bytes, desc)?;
-        save_file(format!("height_map_{}.qoi", side), qoi_bytes)?;
+fn encode_qoi(
+    width: usize,
+    coloring: Coloring,
+    gradient: &Gradient<OkLab, 3>,
+    height_map: &HeightMap,
+    height_maps: &[HeightMap],
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let HeightMap { values, side } = height_map;
+    let mut bytes = Vec::with_capacity(values.len() * 3);
+
+    let mut colors = vec![OkLab::default(); values.len()];
+    gradient.sample_into(values, &mut colors);
+
+    for (j, &color) in colors.iter().enumerate() {
+        let ix = j % width;
+        let iy = j / width;
+        let rgb = coloring.color(gradient, color, (ix, iy), 1, width, *side, height_maps);
+        let [r, g, b] = rgb.to_u8();
+        bytes.push(r);
+        bytes.push(g);
+        bytes.push(b);
     }
 
-    Ok(())
+    let desc = QoiDesc {
+        width: width as u32,
+        height: width as u32,
+        channels: Channels::RGB,
+        color_space: ColorSpace::SRGB,
+    };
+    let qoi_bytes = qoi::encode(&bytes, desc)?;
+    Ok(qoi_bytes)
 }
 
 #[derive(Debug)]
@@ -138,11 +221,23 @@ impl std::fmt::Display for StringError {
 
 impl std::error::Error for StringError {}
 
-fn save_as_qoi_preview<'a>(
+fn save_as_qoi_preview(
+    output: &OutputConfig,
     width: usize,
     preview_width: usize,
-    height_maps: impl IntoIterator<Item = &'a crate::terrain_generator::HeightMap>,
+    coloring: Coloring,
+    height_maps: &[HeightMap],
 ) -> Result<(), Box<dyn std::error::Error>> {
+    let qoi_bytes = encode_qoi_preview(width, preview_width, coloring, height_maps)?;
+    save_file(output.path("preview.qoi"), qoi_bytes)
+}
+
+fn encode_qoi_preview(
+    width: usize,
+    preview_width: usize,
+    coloring: Coloring,
+    height_maps: &[HeightMap],
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
     let preview_width = usize::min(width, preview_width);
 
     if width % preview_width != 0 {
@@ -165,12 +260,15 @@ fn save_as_qoi_preview<'a>(
     let data_len = (desc.width * desc.height * 3) as usize;
     let mut data = vec![u8::MAX; data_len];
 
-    for (i, height_map) in height_maps.into_iter().enumerate() {
+    for (i, height_map) in height_maps.iter().enumerate() {
         let HeightMap { values, side } = height_map;
         eprintln!("serializing preview... {}/6", i + 1);
 
+        let mut heights = vec![0.0; preview_width];
+        let mut colors = vec![OkLab::default(); preview_width];
+
         for iy in 0..preview_width {
-            for ix in 0..preview_width {
+            for (ix, h) in heights.iter_mut().enumerate() {
                 let mut sum = 0.0;
                 for iy_ in 0..kernel_width {
                     for ix_ in 0..kernel_width {
@@ -182,9 +280,21 @@ fn save_as_qoi_preview<'a>(
                     }
                 }
 
-                let h = sum / kernel_len as f32;
-                let lab = gradient.sample(h);
-                let rgb = Rgb::from(lab);
+                *h = sum / kernel_len as f32;
+            }
+
+            gradient.sample_into(&heights, &mut colors);
+
+            for (ix, &color) in colors.iter().enumerate() {
+                let rgb = coloring.color(
+                    &gradient,
+                    color,
+                    (ix * kernel_width, iy * kernel_width),
+                    kernel_width,
+                    width,
+                    *side,
+                    height_maps,
+                );
                 let [r, g, b] = rgb.to_u8();
 
                 let (offset_x, offset_y) = match side {
@@ -208,33 +318,297 @@ fn save_as_qoi_preview<'a>(
     }
 
     let qoi_bytes = qoi::encode(&data, desc)?;
-    save_file("preview.qoi", qoi_bytes)
+    Ok(qoi_bytes)
+}
+
+/// writes the bins, the qois and the preview into a single tar archive at `path`. the archive
+/// also contains a manifest, which lists the width and the size of each file.
+fn save_bundle(
+    path: impl AsRef<Path>,
+    output: &OutputConfig,
+    width: usize,
+    preview_width: usize,
+    coloring: Coloring,
+    height_maps: &[HeightMap],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let gradient = colored_height_gradient()?;
+
+    let file_name = |path: PathBuf| {
+        path.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .ok_or_else(|| StringError(format!("{:?} has no file name", path)))
+    };
+
+    let mut files = Vec::new();
+    for (i, height_map) in height_maps.iter().enumerate() {
+        eprintln!("serializing bundle... {}/6", i + 1);
+        let side = height_map.side;
+
+        let bin_bytes = encode_bin(height_map)?;
+        files.push((file_name(output.side_path(side, "bin"))?, bin_bytes));
+
+        let qoi_bytes = encode_qoi(width, coloring, &gradient, height_map, height_maps)?;
+        files.push((file_name(output.side_path(side, "qoi"))?, qoi_bytes));
+    }
+
+    let preview_bytes = encode_qoi_preview(width, preview_width, coloring, height_maps)?;
+    files.push((file_name(output.path("preview.qoi"))?, preview_bytes));
+
+    let mut manifest = format!("width {}\n", width);
+    for (name, bytes) in files.iter() {
+        manifest.push_str(&format!("{} {}\n", name, bytes.len()));
+    }
+
+    let mut data = std::io::Cursor::new(Vec::new());
+    crate::io::write_tar_entry(&mut data, "manifest.txt", manifest.as_bytes())?;
+    for (name, bytes) in files.iter() {
+        crate::io::write_tar_entry(&mut data, name, bytes)?;
+    }
+    crate::io::write_tar_end(&mut data)?;
+
+    save_file(path, data.into_inner())
+}
+
+fn save_as_qoi_flow_arrows(
+    output: &OutputConfig,
+    width: usize,
+    spacing: usize,
+    height_maps: &[HeightMap],
+) -> Result<(), Box<dyn std::error::Error>> {
+    eprintln!("serializing flow arrows...");
+
+    let data = render_flow_arrows(height_maps, width, spacing)?;
+    let desc = QoiDesc {
+        width: width as u32 * 4,
+        height: width as u32 * 3,
+        channels: Channels::RGB,
+        color_space: ColorSpace::SRGB,
+    };
+
+    let qoi_bytes = qoi::encode(&data, desc)?;
+    save_file(output.path("flow_arrows.qoi"), qoi_bytes)
+}
+
+/// renders the colored height maps in the same layout as the preview, and draws an arrow every
+/// `spacing` pixels, which points downhill. the longer the arrow, the steeper the slope. returns
+/// the RGB bytes of the image, which is `4 * width` pixels wide and `3 * width` pixels high.
+fn render_flow_arrows(
+    height_maps: &[HeightMap],
+    width: usize,
+    spacing: usize,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    if spacing == 0 {
+        Err(StringError("spacing may not be 0".to_string()))?;
+    }
+
+    let gradient = colored_height_gradient()?;
+    let field = terrain_generator::gradient_field(height_maps, width);
+
+    // a few very steep pixels would make all other arrows tiny. thus arrows are scaled relative to
+    // a high percentile instead of the maximum, and clamped
+    let mut slopes = field.iter().map(|g| g.length()).collect::<Vec<_>>();
+    slopes.sort_by(f32::total_cmp);
+    let max_slope = slopes
+        .get(slopes.len() * 95 / 100)
+        .copied()
+        .unwrap_or_default();
+
+    let image_width = width * 4;
+    let mut data = vec![u8::MAX; image_width * width * 3 * 3];
+
+    for (side_index, height_map) in height_maps.iter().enumerate() {
+        let HeightMap { values, side } = height_map;
+
+        let (offset_x, offset_y) = match side {
+            Side::L => (0, width),
+            Side::B => (width, width),
+            Side::R => (2 * width, width),
+            Side::F => (3 * width, width),
+            Side::U => (width, 0),
+            Side::D => (width, 2 * width),
+        };
+
+        let mut plot = |x: f32, y: f32, rgb: [u8; 3]| {
+            if x < 0.0 || y < 0.0 || x >= width as f32 || y >= width as f32 {
+                return;
+            }
+
+            let ix = x as usize + offset_x;
+            let iy = y as usize + offset_y;
+            let i = (iy * image_width + ix) * 3;
+            data[i..i + 3].copy_from_slice(&rgb);
+        };
+
+        // terrain
+        let mut colors = vec![OkLab::default(); values.len()];
+        gradient.sample_into(values, &mut colors);
+        for iy in 0..width {
+            for ix in 0..width {
+                let rgb = Rgb::from(colors[iy * width + ix]).to_u8();
+                plot(ix as f32, iy as f32, rgb);
+            }
+        }
+
+        // arrows
+        if max_slope <= 0.0 {
+            continue;
+        }
+
+        let max_length = (spacing as f32 - 2.0).max(1.0);
+        for iy in (spacing / 2..width).step_by(spacing) {
+            for ix in (spacing / 2..width).step_by(spacing) {
+                let g = field[side_index * width * width + iy * width + ix];
+                let length = max_length * f32::min(g.length() / max_slope, 1.0);
+                let dir = -g.normalize();
+                if dir.is_nan().any() || length < 1.0 {
+                    continue;
+                }
+
+                let start = Vec2(ix as f32 + 0.5, iy as f32 + 0.5);
+                let end = start + dir * length;
+
+                // arrow head: two short lines, rotated by ±150° from the arrow direction
+                let head_length = f32::max(length / 3.0, 2.0);
+                let (sin, cos) = f32::sin_cos(150f32.to_radians());
+                let head0 = Vec2(dir.x() * cos - dir.y() * sin, dir.x() * sin + dir.y() * cos);
+                let head1 = Vec2(
+                    dir.x() * cos + dir.y() * sin,
+                    -dir.x() * sin + dir.y() * cos,
+                );
+
+                let black = [0, 0, 0];
+                draw_line(start, end, |x, y| plot(x, y, black));
+                draw_line(end, end + head0 * head_length, |x, y| plot(x, y, black));
+                draw_line(end, end + head1 * head_length, |x, y| plot(x, y, black));
+            }
+        }
+    }
+
+    Ok(data)
+}
+
+// calls `plot` for every pixel on the line from `start` to `end`
+fn draw_line(start: Vec2, end: Vec2, mut plot: impl FnMut(f32, f32)) {
+    let delta = end - start;
+    let steps = f32::max(delta.x().abs(), delta.y().abs()).ceil() as usize;
+
+    for i in 0..=steps {
+        let t = if steps == 0 {
+            0.0
+        } else {
+            i as f32 / steps as f32
+        };
+        let Vec2(x, y) = start + delta * t;
+        plot(x.floor(), y.floor());
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Coloring {
+    sea_level: f32,
+    coastline_supersamples: usize,
+}
+
+impl Coloring {
+    // colors an area of `footprint * footprint` pixels, starting at `i`, whose average height was
+    // sampled as `color`. if the area contains the coastline, the gradient is evaluated at
+    // `coastline_supersamples²` bilinearly interpolated heights and the colors are averaged. this
+    // smooths the coastline, where the color changes abruptly.
+    #[allow(clippy::too_many_arguments)]
+    fn color(
+        self,
+        gradient: &Gradient<OkLab, 3>,
+        color: OkLab,
+        i: (usize, usize),
+        footprint: usize,
+        width: usize,
+        side: Side,
+        height_maps: &[HeightMap],
+    ) -> Rgb {
+        let n = self.coastline_supersamples;
+        if n <= 1 {
+            return Rgb::from(color);
+        }
+
+        let mut heights = Vec::with_capacity(n * n);
+        for sy in 0..n {
+            for sx in 0..n {
+                // the center of a pixel is at its integer coordinate
+                let x = i.0 as f32 - 0.5 + (sx as f32 + 0.5) / n as f32 * footprint as f32;
+                let y = i.1 as f32 - 0.5 + (sy as f32 + 0.5) / n as f32 * footprint as f32;
+                let h =
+                    terrain_generator::sample_height_map_bilinear((x, y), width, side, height_maps);
+                heights.push(h);
+            }
+        }
+
+        let is_land = |h: f32| h >= self.sea_level;
+        let is_coast = heights.iter().any(|&h| is_land(h) != is_land(heights[0]));
+        if !is_coast {
+            return Rgb::from(color);
+        }
+
+        let mut colors = vec![OkLab::default(); heights.len()];
+        gradient.sample_into(&heights, &mut colors);
+
+        // average in linear light. averaging the gamma encoded channels would darken the coast
+        let mut sum = Rgb::black();
+        for &color in colors.iter() {
+            let Rgb(r, g, b) = Rgb::from(color).to_linear();
+            sum = Rgb(sum.0 + r, sum.1 + g, sum.2 + b);
+        }
+
+        let count = heights.len() as f32;
+        Rgb::from_linear(Rgb(sum.0 / count, sum.1 / count, sum.2 / count))
+    }
 }
 
 fn colored_height_gradient() -> Result<Gradient<OkLab, 3>, Box<dyn std::error::Error>> {
     let gradient = Gradient::try_from([
-        OkLab::from(Rgb::from_hex("#334e9d")?),
-        OkLab::from(Rgb::from_hex("#1d90ff")?),
-        OkLab::from(Rgb::from_hex("#52d83b")?),
-        OkLab::from(Rgb::from_hex("#e4f035")?),
-        OkLab::from(Rgb::from_hex("#ff8b00")?),
-        OkLab::from(Rgb::from_hex("#b01e13")?),
-        OkLab::from(Rgb::from_hex("#7c2b12")?),
+        OkLab::from(named::OCEAN_DEEP),
+        OkLab::from(named::OCEAN_SHALLOW),
+        OkLab::from(named::LOWLAND),
+        OkLab::from(named::HILLS),
+        OkLab::from(named::HIGHLAND),
+        OkLab::from(named::MOUNTAIN),
+        OkLab::from(named::PEAK),
     ])?;
 
     Ok(gradient)
 }
 
+/// where the outputs are written to. files are named `{stem}_{side}.{extension}` for each side,
+/// and `{name}` for files that cover all sides, like `preview.qoi`.
+#[derive(Debug, Clone)]
+struct OutputConfig {
+    dir: PathBuf,
+    stem: String,
+}
+
+impl OutputConfig {
+    fn side_path(&self, side: Side, extension: &str) -> PathBuf {
+        self.dir
+            .join(format!("{}_{}.{}", self.stem, side, extension))
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+}
+
 fn save_file(
-    path: impl AsRef<str>,
+    path: impl AsRef<Path>,
     bytes: impl AsRef<[u8]>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let path = path.as_ref();
+    let filepath = path.as_ref();
     let bytes = bytes.as_ref();
 
-    let filepath = PathBuf::from(path);
+    if let Some(dir) = filepath.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
     if filepath.exists() {
-        std::fs::remove_file(&filepath)?;
+        std::fs::remove_file(filepath)?;
     }
 
     let mut file = std::fs::File::create_new(filepath)?;
@@ -1,5 +1,6 @@
 #![allow(unused)]
 
+use crate::quaternion::Quat;
 use crate::vector::Vec2;
 use crate::vector::Vec3;
 use crate::vector::Vec4;
@@ -79,6 +80,15 @@ impl Mat2 {
     pub fn init(value: f32) -> Self {
         Self(Vec2(value, 0.0), Vec2(0.0, value))
     }
+
+    /// returns the matrix that rotates counter clockwise by `radians`
+    ///
+    /// note that quarter turns are not exact, because `f32::cos(FRAC_PI_2)` is not exactly zero.
+    /// where exact quarter turns are required, write out the matrix instead.
+    pub fn rotation(radians: f32) -> Self {
+        let (sin, cos) = f32::sin_cos(radians);
+        Self(Vec2(cos, sin), Vec2(-sin, cos))
+    }
 }
 
 impl Mat3 {
@@ -93,6 +103,30 @@ impl Mat3 {
             Vec3(0.0, 0.0, value),
         )
     }
+
+    /// returns the rotation matrix of `q`. `q` is expected to be normalized. rotating a vector by
+    /// the returned matrix is equivalent to `q.rotate(v)`.
+    pub fn from_quat(q: Quat) -> Self {
+        let Quat(x, y, z, w) = q;
+
+        Self(
+            Vec3(
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y + w * z),
+                2.0 * (x * z - w * y),
+            ),
+            Vec3(
+                2.0 * (x * y - w * z),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z + w * x),
+            ),
+            Vec3(
+                2.0 * (x * z + w * y),
+                2.0 * (y * z - w * x),
+                1.0 - 2.0 * (x * x + y * y),
+            ),
+        )
+    }
 }
 
 impl Mat4 {
@@ -108,6 +142,18 @@ impl Mat4 {
             Vec4(0.0, 0.0, 0.0, value),
         )
     }
+
+    /// returns the matrix that translates points by `t`
+    pub fn from_translation(t: Vec3) -> Self {
+        let mut r = Self::identity();
+        r.3 = Vec4(t.0, t.1, t.2, 1.0);
+        r
+    }
+
+    /// returns `m` in the upper left corner of the identity matrix. same as `Mat4::from(m)`.
+    pub fn from_mat3(m: Mat3) -> Self {
+        Self::from(m)
+    }
 }
 
 impl From<Mat2> for Mat3 {
@@ -462,6 +508,31 @@ impl std::ops::IndexMut<usize> for Mat4 {
     }
 }
 
+//
+// display
+//
+
+/// prints one row per line, with the columns aligned. the precision of the formatter is applied to
+/// every component, i.e. `format!("{:.2}", m)`.
+impl std::fmt::Display for Mat2 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let format = |value: f32| match f.precision() {
+            Some(precision) => format!("{:.*}", precision, value),
+            None => format!("{}", value),
+        };
+
+        let Mat2(Vec2(a, b), Vec2(c, d)) = *self;
+        let (a, b, c, d) = (format(a), format(b), format(c), format(d));
+        let w0 = a.len().max(b.len());
+        let w1 = c.len().max(d.len());
+
+        writeln!(f, "[{:>w0$} {:>w1$}]", a, c)?;
+        write!(f, "[{:>w0$} {:>w1$}]", b, d)?;
+
+        Ok(())
+    }
+}
+
 //
 // operations
 //
@@ -693,10 +764,11 @@ impl Mat2 {
         a * d - b * c
     }
 
-    /// returns a matrix that is the inverse of self
+    /// returns a matrix that is the inverse of self. returns `None` if the determinant is close to
+    /// zero, regardless of its sign
     pub fn inverse(self) -> Option<Self> {
         let det = self.determinant();
-        if det < MIN_NORM {
+        if det.abs() < MIN_NORM {
             return None; // matrix is not invertible
         }
 
@@ -1003,6 +1075,38 @@ impl Mat3 {
 
         Some(r)
     }
+
+    /// returns the quaternion of the rotation matrix self. self is expected to be a rotation, i.e.
+    /// orthonormal with a determinant of 1. this is the inverse of `from_quat`, but the result
+    /// may be the negated quaternion, which represents the same rotation.
+    pub fn to_quat(self) -> Quat {
+        let m00 = self.0 .0;
+        let m10 = self.0 .1;
+        let m20 = self.0 .2;
+        let m01 = self.1 .0;
+        let m11 = self.1 .1;
+        let m21 = self.1 .2;
+        let m02 = self.2 .0;
+        let m12 = self.2 .1;
+        let m22 = self.2 .2;
+
+        // divide by the largest of the four candidates, to avoid dividing by something close to
+        // zero: https://en.wikipedia.org/wiki/Rotation_matrix#Quaternion
+        let trace = m00 + m11 + m22;
+        if trace > 0. {
+            let s = f32::sqrt(trace + 1.) * 2.;
+            Quat((m21 - m12) / s, (m02 - m20) / s, (m10 - m01) / s, 0.25 * s)
+        } else if m00 > m11 && m00 > m22 {
+            let s = f32::sqrt(1. + m00 - m11 - m22) * 2.;
+            Quat(0.25 * s, (m01 + m10) / s, (m02 + m20) / s, (m21 - m12) / s)
+        } else if m11 > m22 {
+            let s = f32::sqrt(1. + m11 - m00 - m22) * 2.;
+            Quat((m01 + m10) / s, 0.25 * s, (m12 + m21) / s, (m02 - m20) / s)
+        } else {
+            let s = f32::sqrt(1. + m22 - m00 - m11) * 2.;
+            Quat((m02 + m20) / s, (m12 + m21) / s, 0.25 * s, (m10 - m01) / s)
+        }
+    }
 }
 
 impl Mat3x4 {
@@ -1372,4 +1476,91 @@ impl Mat4 {
 
         Some(r)
     }
+
+    /// returns the components column by column, which is the layout of `Mat4` in memory
+    pub fn to_cols_array(self) -> [f32; 16] {
+        let mut r = [0.0; 16];
+        for i in 0..4 {
+            r[i * 4..i * 4 + 4].copy_from_slice(&<[f32; 4]>::from(self[i]));
+        }
+        r
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mat3_from_identity_quat_is_identity() {
+        assert_eq!(Mat3::from_quat(Quat::identity()), Mat3::identity());
+    }
+
+    #[test]
+    fn mat3_from_quat_rotates_like_quat() {
+        let q = Quat::angle_axis(0.7, Vec3(1.0, 2.0, 3.0).normalize());
+        let m = Mat3::from_quat(q);
+
+        for v in [
+            Vec3(1.0, 0.0, 0.0),
+            Vec3(0.0, 1.0, 0.0),
+            Vec3(0.0, 0.0, 1.0),
+        ] {
+            assert!((m * v).approx_eq(q.rotate(v), 0.000_01));
+        }
+    }
+
+    #[test]
+    fn mat2_inverse_of_rotation_is_transpose() {
+        let m = Mat2(Vec2(0.6, 0.8), Vec2(-0.8, 0.6));
+        let inverse = m.inverse().unwrap();
+        let transpose = m.transpose();
+
+        assert!(inverse.0.approx_eq(transpose.0, 0.000_01));
+        assert!(inverse.1.approx_eq(transpose.1, 0.000_01));
+    }
+
+    #[test]
+    fn mat2_singular_has_no_inverse() {
+        assert_eq!(Mat2(Vec2(1.0, 2.0), Vec2(2.0, 4.0)).inverse(), None);
+        assert_eq!(Mat2(Vec2(-1.0, 2.0), Vec2(2.0, -4.0)).inverse(), None);
+    }
+
+    #[test]
+    fn mat2_rotation_is_counterclockwise() {
+        let m = Mat2::rotation(std::f32::consts::FRAC_PI_2);
+        assert!((m * Vec2(1.0, 0.0)).approx_eq(Vec2(0.0, 1.0), 0.000_001));
+        assert!((m * Vec2(0.0, 1.0)).approx_eq(Vec2(-1.0, 0.0), 0.000_001));
+    }
+
+    #[test]
+    fn mat4_identity_is_neutral() {
+        let m = Mat4(
+            Vec4(1.0, 2.0, 3.0, 4.0),
+            Vec4(5.0, 6.0, 7.0, 8.0),
+            Vec4(9.0, 10.0, 11.0, 12.0),
+            Vec4(13.0, 14.0, 15.0, 16.0),
+        );
+        assert_eq!(Mat4::identity() * m, m);
+        assert_eq!(m * Mat4::identity(), m);
+    }
+
+    #[test]
+    fn mat4_translations_compose() {
+        let a = Mat4::from_translation(Vec3(1.0, 2.0, 3.0));
+        let b = Mat4::from_translation(Vec3(-4.0, 0.5, 2.0));
+        assert_eq!(a * b, Mat4::from_translation(Vec3(-3.0, 2.5, 5.0)));
+
+        let p = Vec4(1.0, 1.0, 1.0, 1.0);
+        assert_eq!(a * b * p, Vec4(-2.0, 3.5, 6.0, 1.0));
+    }
+
+    #[test]
+    fn mat2_display_prints_rows() {
+        assert_eq!(Mat2::identity().to_string(), "[1 0]\n[0 1]");
+        assert_eq!(
+            format!("{:.1}", Mat2(Vec2(1.0, -2.0), Vec2(10.0, 0.5))),
+            "[ 1.0 10.0]\n[-2.0  0.5]"
+        );
+    }
 }
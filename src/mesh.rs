@@ -0,0 +1,360 @@
+#![allow(unused)]
+
+use std::collections::HashMap;
+use std::io::Result;
+use std::io::Seek;
+use std::io::Write;
+
+use crate::terrain_generator::position_on_sphere;
+use crate::terrain_generator::sample_height_map;
+use crate::terrain_generator::HeightMap;
+use crate::terrain_generator::Side;
+use crate::terrain_generator::VerticalScale;
+use crate::vector::Vec3;
+
+//
+// mesh
+//
+
+#[derive(Debug, Default, Clone)]
+pub struct Mesh {
+    pub vertices: Vec<Vec3>,
+    pub triangles: Vec<[u32; 3]>,
+}
+
+impl Mesh {
+    /// writes the mesh as a wavefront obj. triangles are wound counterclockwise, when viewed from
+    /// outside of the sphere.
+    pub fn write_obj(&self, out: &mut impl Write) -> Result<()> {
+        for Vec3(x, y, z) in self.vertices.iter() {
+            writeln!(out, "v {} {} {}", x, y, z)?;
+        }
+
+        // obj indices start at 1
+        for [a, b, c] in self.triangles.iter() {
+            writeln!(out, "f {} {} {}", a + 1, b + 1, c + 1)?;
+        }
+
+        Ok(())
+    }
+
+    /// writes the mesh as a binary little endian ply.
+    pub fn write_ply(&self, out: &mut (impl Write + Seek)) -> Result<()> {
+        crate::io::write_ply_mesh(out, &self.vertices, &[], &self.triangles)?;
+        Ok(())
+    }
+}
+
+// vertices lie on the corners of the pixels, thus there are `width + 1` vertices along each axis of
+// a side. vertices on the edges of the cube are shared between sides. to find them, they are
+// identified by their integer position on the cube, which is the same no matter from which side
+// they are looked up.
+type VertexKey = (isize, isize, isize);
+
+fn vertex_key(ix: usize, iy: usize, width: usize, side: Side) -> VertexKey {
+    let w = width as isize;
+    let x = 2 * ix as isize - w;
+    let y = 2 * iy as isize - w;
+
+    // same mapping as `position_on_sphere`
+    match side {
+        Side::L => (-w, -x, -y),
+        Side::B => (x, -w, -y),
+        Side::R => (w, x, -y),
+        Side::F => (-x, w, -y),
+        Side::U => (x, -y, w),
+        Side::D => (x, y, -w),
+    }
+}
+
+// the height of a vertex is the average of the `(2 * radius)²` pixels around it. since pixels are
+// looked up over the edges of the cube, a vertex on an edge gets the same height, no matter from
+// which side it is looked up.
+fn vertex_height(
+    ix: usize,
+    iy: usize,
+    radius: usize,
+    width: usize,
+    side: Side,
+    height_maps: &[HeightMap],
+) -> f32 {
+    let ix = ix as isize;
+    let iy = iy as isize;
+    let r = radius as isize;
+
+    let mut sum = 0.0;
+    for y in iy - r..iy + r {
+        for x in ix - r..ix + r {
+            sum += sample_height_map((x, y), width, side, height_maps);
+        }
+    }
+
+    sum / (4 * radius * radius) as f32
+}
+
+struct MeshBuilder {
+    mesh: Mesh,
+    indices: HashMap<VertexKey, u32>,
+}
+
+impl MeshBuilder {
+    fn new() -> Self {
+        Self {
+            mesh: Mesh::default(),
+            indices: HashMap::new(),
+        }
+    }
+
+    fn contains(&self, ix: usize, iy: usize, width: usize, side: Side) -> bool {
+        self.indices.contains_key(&vertex_key(ix, iy, width, side))
+    }
+
+    // returns the index of the vertex. if it does not exist yet, it is added, with the height
+    // returned by `height`
+    fn vertex(
+        &mut self,
+        ix: usize,
+        iy: usize,
+        width: usize,
+        side: Side,
+        vertical_scale: VerticalScale,
+        height: impl FnOnce() -> f32,
+    ) -> u32 {
+        let key = vertex_key(ix, iy, width, side);
+        if let Some(&index) = self.indices.get(&key) {
+            return index;
+        }
+
+        let p = position_on_sphere((ix, iy), width, side);
+        let index = self.mesh.vertices.len() as u32;
+        self.mesh
+            .vertices
+            .push(vertical_scale.displace(p, height()));
+        self.indices.insert(key, index);
+        index
+    }
+
+    // adds a triangle, flipping it if necessary, such that it faces away from the center of the
+    // sphere
+    fn triangle(&mut self, a: u32, b: u32, c: u32) {
+        let vertices = &self.mesh.vertices;
+        let pa = vertices[a as usize];
+        let pb = vertices[b as usize];
+        let pc = vertices[c as usize];
+
+        let n = Vec3::cross(pb - pa, pc - pa);
+        if Vec3::dot(n, pa + pb + pc) < 0.0 {
+            self.mesh.triangles.push([a, c, b]);
+        } else {
+            self.mesh.triangles.push([a, b, c]);
+        }
+    }
+}
+
+//
+// uniform mesh
+//
+
+/// builds a mesh of the sphere displaced by the height maps, with `target_res * target_res` quads
+/// per side. the height of each vertex is the average of the pixels around it, thus the height maps
+/// are downsampled without introducing seams, and vertices on the edges of the cube are shared
+/// between sides. the height is displaced according to `vertical_scale`. `width` must be a multiple
+/// of `target_res`. `height_maps` must be ordered like the result of `run`. the mesh is written to
+/// `out` as a wavefront obj.
+pub fn export_mesh_lod(
+    height_maps: &[HeightMap],
+    width: usize,
+    target_res: usize,
+    vertical_scale: VerticalScale,
+    out: &mut impl Write,
+) -> Result<()> {
+    mesh_lod(height_maps, width, target_res, vertical_scale).write_obj(out)
+}
+
+/// see `export_mesh_lod`
+pub fn mesh_lod(
+    height_maps: &[HeightMap],
+    width: usize,
+    target_res: usize,
+    vertical_scale: VerticalScale,
+) -> Mesh {
+    assert!(target_res > 0 && width % target_res == 0);
+
+    let step = width / target_res;
+    let radius = usize::max(step / 2, 1);
+    let mut builder = MeshBuilder::new();
+
+    for height_map in height_maps.iter() {
+        let side = height_map.side;
+
+        let mut vertex = |x: usize, y: usize| {
+            let ix = x * step;
+            let iy = y * step;
+            let height = || vertex_height(ix, iy, radius, width, side, height_maps);
+            builder.vertex(ix, iy, width, side, vertical_scale, height)
+        };
+
+        let mut quads = Vec::with_capacity(target_res * target_res);
+        for y in 0..target_res {
+            for x in 0..target_res {
+                let a = vertex(x, y);
+                let b = vertex(x + 1, y);
+                let c = vertex(x + 1, y + 1);
+                let d = vertex(x, y + 1);
+                quads.push([a, b, c, d]);
+            }
+        }
+
+        for [a, b, c, d] in quads {
+            builder.triangle(a, b, c);
+            builder.triangle(a, c, d);
+        }
+    }
+
+    builder.mesh
+}
+
+//
+// adaptive mesh
+//
+
+struct QuadtreeLeaf {
+    ix: usize,
+    iy: usize,
+    size: usize,
+    side: Side,
+}
+
+/// builds a mesh of the sphere displaced by the height maps, with a quadtree per side. a node is
+/// subdivided, when the heights inside it deviate from the bilinear interpolation of its corners
+/// by more than `error_threshold`. flat regions thus end up with few, large triangles, while rough
+/// regions keep their full resolution. each leaf is triangulated as a fan around its center,
+/// including all vertices of smaller neighbors on its edges, such that no cracks appear between
+/// different levels of detail, neither on a side nor over the edges of the cube. the height is
+/// displaced according to `vertical_scale`. `width` must be a power of two.
+/// `height_maps` must be ordered like the result of `run`. the mesh is written to `out` as a
+/// wavefront obj.
+pub fn export_adaptive_mesh(
+    height_maps: &[HeightMap],
+    width: usize,
+    error_threshold: f32,
+    vertical_scale: VerticalScale,
+    out: &mut impl Write,
+) -> Result<()> {
+    adaptive_mesh(height_maps, width, error_threshold, vertical_scale).write_obj(out)
+}
+
+/// see `export_adaptive_mesh`
+pub fn adaptive_mesh(
+    height_maps: &[HeightMap],
+    width: usize,
+    error_threshold: f32,
+    vertical_scale: VerticalScale,
+) -> Mesh {
+    assert!(width.is_power_of_two());
+
+    let vertex_width = width + 1;
+    let mut leaves = Vec::new();
+
+    for height_map in height_maps.iter() {
+        let side = height_map.side;
+
+        let mut heights = Vec::with_capacity(vertex_width * vertex_width);
+        for iy in 0..vertex_width {
+            for ix in 0..vertex_width {
+                heights.push(vertex_height(ix, iy, 1, width, side, height_maps));
+            }
+        }
+        let height = |ix: usize, iy: usize| heights[ix + iy * vertex_width];
+
+        let mut stack = vec![(0, 0, width)];
+        while let Some((ix, iy, size)) = stack.pop() {
+            let h00 = height(ix, iy);
+            let h10 = height(ix + size, iy);
+            let h01 = height(ix, iy + size);
+            let h11 = height(ix + size, iy + size);
+
+            let mut error = 0.0f32;
+            for y in 0..=size {
+                for x in 0..=size {
+                    let tx = x as f32 / size as f32;
+                    let ty = y as f32 / size as f32;
+                    let top = h00 + (h10 - h00) * tx;
+                    let bottom = h01 + (h11 - h01) * tx;
+                    let planar = top + (bottom - top) * ty;
+                    error = f32::max(error, f32::abs(height(ix + x, iy + y) - planar));
+                }
+            }
+
+            if size > 1 && error > error_threshold {
+                let half = size / 2;
+                stack.push((ix, iy, half));
+                stack.push((ix + half, iy, half));
+                stack.push((ix, iy + half, half));
+                stack.push((ix + half, iy + half, half));
+            } else {
+                leaves.push(QuadtreeLeaf { ix, iy, size, side });
+            }
+        }
+    }
+
+    let mut builder = MeshBuilder::new();
+    let height = |ix, iy, side| move || vertex_height(ix, iy, 1, width, side, height_maps);
+
+    // add the corners of all leaves first, such that every leaf knows which vertices lie on its
+    // edges
+    for &QuadtreeLeaf { ix, iy, size, side } in leaves.iter() {
+        for (x, y) in [
+            (ix, iy),
+            (ix + size, iy),
+            (ix, iy + size),
+            (ix + size, iy + size),
+        ] {
+            builder.vertex(x, y, width, side, vertical_scale, height(x, y, side));
+        }
+    }
+
+    for &QuadtreeLeaf { ix, iy, size, side } in leaves.iter() {
+        // walk around the edges of the leaf
+        let mut perimeter = Vec::new();
+        let edges = [
+            ((ix, iy), (1, 0)),
+            ((ix + size, iy), (0, 1)),
+            ((ix + size, iy + size), (-1, 0)),
+            ((ix, iy + size), (0, -1)),
+        ];
+        for ((x, y), (dx, dy)) in edges {
+            for i in 0..size as isize {
+                let x = (x as isize + dx * i) as usize;
+                let y = (y as isize + dy * i) as usize;
+                if builder.contains(x, y, width, side) {
+                    perimeter.push(builder.vertex(
+                        x,
+                        y,
+                        width,
+                        side,
+                        vertical_scale,
+                        height(x, y, side),
+                    ));
+                }
+            }
+        }
+
+        if perimeter.len() == 4 {
+            builder.triangle(perimeter[0], perimeter[1], perimeter[2]);
+            builder.triangle(perimeter[0], perimeter[2], perimeter[3]);
+        } else {
+            let x = ix + size / 2;
+            let y = iy + size / 2;
+            let center = builder.vertex(x, y, width, side, vertical_scale, height(x, y, side));
+
+            for i in 0..perimeter.len() {
+                let a = perimeter[i];
+                let b = perimeter[(i + 1) % perimeter.len()];
+                builder.triangle(center, a, b);
+            }
+        }
+    }
+
+    builder.mesh
+}
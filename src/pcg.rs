@@ -1,3 +1,7 @@
+#![allow(unused)]
+
+use crate::rng::Seed;
+
 #[derive(Debug)]
 pub struct Pcg32 {
     pub state: u64,
@@ -24,6 +28,32 @@ impl Pcg32 {
         result
     }
 
+    /// seeds the generator like `pcg32_srandom_r` of the reference implementation:
+    /// https://www.pcg-random.org/. generators with different `stream`s produce different
+    /// sequences, even when `seed` is the same.
+    pub fn with_stream(seed: u64, stream: u64) -> Pcg32 {
+        let mut result = Pcg32 {
+            state: 0,
+            increment: (stream << 1) | 1,
+        };
+
+        result.next();
+        result.state = result.state.wrapping_add(seed);
+        result.next();
+
+        result
+    }
+
+    /// same as `next`
+    pub fn next_u32(&mut self) -> u32 {
+        self.next()
+    }
+
+    /// returns a f32 in [0.0, 1.0), see `Rng::next_f32`
+    pub fn next_f32(&mut self) -> f32 {
+        crate::rng::Rng::hash_to_f32(self.next())
+    }
+
     pub fn next(&mut self) -> u32 {
         const MULTIPLIER: u64 = 6364136223846793005;
         const XSHIFT: u32 = 18;
@@ -42,3 +72,85 @@ impl Pcg32 {
         xorshifted.rotate_right(rot)
     }
 }
+
+/// hashes the integer coordinates `x` and `y` together with the lower 64 bits of `seed`. the
+/// result is deterministic, i.e. the same inputs always result in the same hash. used to pick
+/// the gradients of the perlin noise.
+pub fn hash2(x: i32, y: i32, seed: Seed) -> u32 {
+    let Seed(seed_value) = seed;
+    let seed_a = seed_value & 0xFFFFFFFF;
+    let seed_b = (seed_value >> 32) & 0xFFFFFFFF;
+
+    let w = (8 * std::mem::size_of::<u32>()) as u32;
+    let s = w / 2;
+    let a = (x as u32) ^ (seed_a as u32);
+    let b = (y as u32) ^ (seed_b as u32);
+    let a = a.wrapping_mul(3284157443);
+    let b = b ^ ((a << s) | (a >> (w - s)));
+    let b = b.wrapping_mul(1911520717);
+    let a = a ^ ((b << s) | (b >> (w - s)));
+    a.wrapping_mul(2048419325)
+}
+
+/// same as `hash2`, but for three coordinates. mixes in bits 64 to 95 of `seed` for `z`.
+pub fn hash3(x: i32, y: i32, z: i32, seed: Seed) -> u32 {
+    let Seed(seed_value) = seed;
+    let seed_a = seed_value & 0xFFFFFFFF;
+    let seed_b = (seed_value >> 32) & 0xFFFFFFFF;
+    let seed_c = (seed_value >> 64) & 0xFFFFFFFF;
+
+    let w = (8 * std::mem::size_of::<u32>()) as u32;
+    let s = w / 2;
+    let a = (x as u32) ^ (seed_a as u32);
+    let b = (y as u32) ^ (seed_b as u32);
+    let c = (z as u32) ^ (seed_c as u32);
+    let a = a.wrapping_mul(3284157443);
+    let b = b ^ ((a << s) | (a >> (w - s)));
+    let b = b.wrapping_mul(1911520717);
+    let c = c ^ ((b << s) | (b >> (w - s)));
+    let c = c.wrapping_mul(3284157443);
+    let a = a ^ ((c << s) | (c >> (w - s)));
+    a.wrapping_mul(2048419325)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn with_stream_matches_reference() {
+        // reference output of the pcg32 demo, seeded with `pcg32_srandom_r(&rng, 42u, 54u)`
+        let expected = [
+            0xa15c02b7, 0x7b47f409, 0xba1d3330, 0x83d2f293, 0xbfa4784b, 0xcbed606e,
+        ];
+
+        let mut pcg = Pcg32::with_stream(42, 54);
+        for value in expected {
+            assert_eq!(pcg.next_u32(), value);
+        }
+    }
+
+    #[test]
+    fn hash2_is_stable() {
+        // the perlin gradients depend on these, changing them changes every generated planet
+        assert_eq!(hash2(1, 2, Seed(0)), 1341445860);
+        assert_eq!(hash2(2, 1, Seed(0)), 78628481);
+        assert_eq!(hash2(-1, 5, Seed(0)), 3848568066);
+        assert_eq!(hash2(1, 2, Seed(1)), 3972647014);
+        assert_eq!(hash2(1, 2, Seed(1 << 40)), 2233174354);
+    }
+
+    #[test]
+    fn hash3_is_stable_and_depends_on_order() {
+        let seed = Seed::default();
+        assert_eq!(hash3(1, 2, 3, seed), 1970061046);
+        assert_eq!(hash3(1, 3, 2, seed), 1291811753);
+        assert_eq!(hash3(2, 1, 3, seed), 1570313775);
+        assert_eq!(hash3(2, 3, 1, seed), 4086819145);
+        assert_eq!(hash3(3, 1, 2, seed), 250942911);
+        assert_eq!(hash3(3, 2, 1, seed), 631823520);
+
+        assert_eq!(hash3(1, 2, 3, Seed(1)), 81997243);
+        assert_eq!(hash3(1, 2, 3, Seed(1 << 70)), 4189509627);
+    }
+}
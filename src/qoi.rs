@@ -5,6 +5,8 @@
 // original format and C encoder/decoder by Dominic Szablewski: https://qoiformat.org/
 
 use std::io::Cursor;
+use std::io::Write;
+use std::path::Path;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct QoiDesc {
@@ -110,7 +112,7 @@ pub enum EncodeErrorKind {
     WidthIsZero,
     HeightIsZero,
     DimensionsTooLarge,
-    DataDoesNotMatchDimensions,
+    DataDoesNotMatchDimensions { expected: usize, got: usize },
     IoError(std::io::Error),
 }
 
@@ -118,6 +120,8 @@ pub enum EncodeErrorKind {
 pub enum DecodeErrorKind {
     DataToSmall,
     IncorrectMagic,
+    MissingEndMarker,
+    UnexpectedEof,
     DescWidthIsZero,
     DescHeightIsZero,
     IoError(std::io::Error),
@@ -161,9 +165,11 @@ impl std::fmt::Display for EncodeError {
             EncodeErrorKind::DimensionsTooLarge => {
                 write!(f, "pixels may not exceed {}", PIXELS_MAX)
             }
-            EncodeErrorKind::DataDoesNotMatchDimensions => {
-                write!(f, "data must have length of width * height * channels")
-            }
+            EncodeErrorKind::DataDoesNotMatchDimensions { expected, got } => write!(
+                f,
+                "data must have length of width * height * channels = {}, but was {}",
+                expected, got,
+            ),
             EncodeErrorKind::IoError(e) => write!(f, "io error occured: {}", e),
         }
     }
@@ -172,8 +178,12 @@ impl std::fmt::Display for EncodeError {
 impl std::fmt::Display for DecodeError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match &self.kind {
-            DecodeErrorKind::DataToSmall => write!(f, "data must be larger than {}", DATA_MIN),
+            DecodeErrorKind::DataToSmall => write!(f, "data is too small to be qoi"),
             DecodeErrorKind::IncorrectMagic => write!(f, "magic must be {:?}", MAGIC),
+            DecodeErrorKind::MissingEndMarker => write!(f, "data must end with {:?}", PADDING),
+            DecodeErrorKind::UnexpectedEof => {
+                write!(f, "data ended before all pixels were decoded")
+            }
             DecodeErrorKind::DescWidthIsZero => write!(f, "decoded header width was 0"),
             DecodeErrorKind::DescHeightIsZero => write!(f, "decoded header height was 0"),
             DecodeErrorKind::IoError(e) => write!(f, "io error occured: {}", e),
@@ -183,6 +193,39 @@ impl std::fmt::Display for DecodeError {
 }
 
 pub fn encode(data: &[u8], desc: QoiDesc) -> Result<Vec<u8>, EncodeError> {
+    validate_desc(desc)?;
+
+    let px_len = desc.width as usize * desc.height as usize * desc.channels as usize;
+    if px_len != data.len() {
+        return Err(EncodeError {
+            kind: EncodeErrorKind::DataDoesNotMatchDimensions {
+                expected: px_len,
+                got: data.len(),
+            },
+        });
+    }
+
+    let max_size =
+        desc.width * desc.height * (desc.channels as u32 + 1) + HEADER_SIZE + PADDING.len() as u32;
+
+    let bytes = Cursor::new(Vec::with_capacity(max_size as usize));
+    let mut encoder = QoiEncoder::new(bytes, desc)?;
+
+    for px in data.chunks_exact(desc.channels as usize) {
+        let a = if desc.channels == Channels::RGBA {
+            px[3]
+        } else {
+            255
+        };
+
+        encoder.push_pixel([px[0], px[1], px[2], a])?;
+    }
+
+    let result = encoder.finish()?.into_inner();
+    Ok(result)
+}
+
+fn validate_desc(desc: QoiDesc) -> Result<(), EncodeError> {
     if desc.width == 0 {
         return Err(EncodeError {
             kind: EncodeErrorKind::WidthIsZero,
@@ -201,102 +244,146 @@ pub fn encode(data: &[u8], desc: QoiDesc) -> Result<Vec<u8>, EncodeError> {
         });
     }
 
-    let max_size =
-        desc.width * desc.height * (desc.channels as u32 + 1) + HEADER_SIZE + PADDING.len() as u32;
+    Ok(())
+}
 
-    let mut bytes = Cursor::new(Vec::with_capacity(max_size as usize));
+/// encodes an image pixel by pixel, writing the chunks directly to `writer`. unlike `encode`, this
+/// doesn't require the whole image to be in memory. the header is written by `new`, the end marker
+/// by `finish`. produces the same bytes as `encode`.
+pub struct QoiEncoder<W: Write> {
+    writer: W,
+    desc: QoiDesc,
+    index: [Rgba; 64],
+    px_prev: Rgba,
+    run: u8,
+    px_count: usize,
+}
 
-    crate::io::write(&mut bytes, &MAGIC)?;
-    crate::io::write(&mut bytes, &desc.width.to_be_bytes())?;
-    crate::io::write(&mut bytes, &desc.height.to_be_bytes())?;
-    crate::io::write(&mut bytes, &[desc.channels as u8, desc.color_space as u8])?;
+impl<W: Write> QoiEncoder<W> {
+    pub fn new(mut writer: W, desc: QoiDesc) -> Result<Self, EncodeError> {
+        validate_desc(desc)?;
+
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&desc.width.to_be_bytes())?;
+        writer.write_all(&desc.height.to_be_bytes())?;
+        writer.write_all(&[desc.channels as u8, desc.color_space as u8])?;
+
+        Ok(Self {
+            writer,
+            desc,
+            index: [Rgba::default(); 64],
+            px_prev: Rgba::from_bytes(&[0, 0, 0, 255]),
+            run: 0,
+            px_count: 0,
+        })
+    }
 
-    let pixels = data;
+    /// pushes the next pixel, in row major order. if the image has 3 channels, the alpha is
+    /// ignored.
+    pub fn push_pixel(&mut self, rgba: [u8; 4]) -> Result<(), EncodeError> {
+        let pixels = self.pixels();
+        if self.px_count >= pixels {
+            return Err(EncodeError {
+                kind: EncodeErrorKind::DataDoesNotMatchDimensions {
+                    expected: pixels * self.desc.channels as usize,
+                    got: (self.px_count + 1) * self.desc.channels as usize,
+                },
+            });
+        }
+        self.px_count += 1;
 
-    let mut index = [Rgba::default(); 64];
+        let mut px = Rgba::from_bytes(&rgba);
+        if self.desc.channels == Channels::RGB {
+            px.a = 255;
+        }
 
-    let mut run = 0;
-    let mut px_prev = Rgba::from_bytes(&[0, 0, 0, 255]);
-    let mut px = px_prev;
+        let px_prev = self.px_prev;
+        self.px_prev = px;
 
-    let px_len = desc.width as usize * desc.height as usize * desc.channels as usize;
-    let px_end = px_len - desc.channels as usize;
-    let channels = desc.channels as u32;
+        if px == px_prev {
+            self.run += 1;
+            if self.run == 62 {
+                self.flush_run()?;
+            }
 
-    if px_len != pixels.len() {
-        return Err(EncodeError {
-            kind: EncodeErrorKind::DataDoesNotMatchDimensions,
-        });
-    }
+            return Ok(());
+        }
+
+        self.flush_run()?;
 
-    for px_pos in (0..px_len).step_by(channels as usize) {
-        px.r = pixels[px_pos];
-        px.g = pixels[px_pos + 1];
-        px.b = pixels[px_pos + 2];
+        let index_pos = px.hash() % 64;
 
-        if channels == 4 {
-            px.a = pixels[px_pos + 3];
+        if self.index[index_pos as usize] == px {
+            self.writer.write_all(&[OP_INDEX | index_pos])?;
+            return Ok(());
         }
 
-        if px == px_prev {
-            run += 1;
-            if run == 62 || px_pos == px_end {
-                crate::io::write(&mut bytes, &[OP_RUN | (run - 1)])?;
-                run = 0;
+        self.index[index_pos as usize] = px;
+
+        if px.a == px_prev.a {
+            let vr = (px.r as i8).wrapping_sub(px_prev.r as i8);
+            let vg = (px.g as i8).wrapping_sub(px_prev.g as i8);
+            let vb = (px.b as i8).wrapping_sub(px_prev.b as i8);
+
+            let vg_r = vr.wrapping_sub(vg);
+            let vg_b = vb.wrapping_sub(vg);
+
+            if vr > -3 && vr < 2 && vg > -3 && vg < 2 && vb > -3 && vb < 2 {
+                let dr = ((vr + 2) << 4) as u8;
+                let dg = ((vg + 2) << 2) as u8;
+                let db = (vb + 2) as u8;
+                self.writer.write_all(&[OP_DIFF | dr | dg | db])?;
+            } else if vg_r > -9 && vg_r < 8 && vg > -33 && vg < 32 && vg_b > -9 && vg_b < 8 {
+                let dr = ((vg_r + 8) << 4) as u8;
+                let dg = (vg + 32) as u8;
+                let db = (vg_b + 8) as u8;
+                self.writer.write_all(&[OP_LUMA | dg, dr | db])?;
+            } else {
+                self.writer.write_all(&[OP_RGB, px.r, px.g, px.b])?;
             }
         } else {
-            if run > 0 {
-                crate::io::write(&mut bytes, &[OP_RUN | (run - 1)])?;
-                run = 0;
-            }
+            self.writer.write_all(&[OP_RGBA, px.r, px.g, px.b, px.a])?;
+        }
 
-            let index_pos = px.hash() % 64;
+        Ok(())
+    }
 
-            if index[index_pos as usize] == px {
-                crate::io::write(&mut bytes, &[OP_INDEX | index_pos])?;
-            } else {
-                index[index_pos as usize] = px;
-
-                if px.a == px_prev.a {
-                    let vr = (px.r as i8).wrapping_sub(px_prev.r as i8);
-                    let vg = (px.g as i8).wrapping_sub(px_prev.g as i8);
-                    let vb = (px.b as i8).wrapping_sub(px_prev.b as i8);
-
-                    let vg_r = vr.wrapping_sub(vg);
-                    let vg_b = vb.wrapping_sub(vg);
-
-                    if vr > -3 && vr < 2 && vg > -3 && vg < 2 && vb > -3 && vb < 2 {
-                        let dr = ((vr + 2) << 4) as u8;
-                        let dg = ((vg + 2) << 2) as u8;
-                        let db = (vb + 2) as u8;
-                        crate::io::write(&mut bytes, &[OP_DIFF | dr | dg | db])?;
-                    } else if vg_r > -9 && vg_r < 8 && vg > -33 && vg < 32 && vg_b > -9 && vg_b < 8
-                    {
-                        let dr = ((vg_r + 8) << 4) as u8;
-                        let dg = (vg + 32) as u8;
-                        let db = (vg_b + 8) as u8;
-                        crate::io::write(&mut bytes, &[OP_LUMA | dg])?;
-                        crate::io::write(&mut bytes, &[dr | db])?;
-                    } else {
-                        crate::io::write(&mut bytes, &[OP_RGB, px.r, px.g, px.b])?;
-                    }
-                } else {
-                    crate::io::write(&mut bytes, &[OP_RGBA, px.r, px.g, px.b, px.a])?;
-                }
-            }
+    /// writes the end marker and returns the writer. fails if fewer pixels were pushed than the
+    /// descriptor requires.
+    pub fn finish(mut self) -> Result<W, EncodeError> {
+        let pixels = self.pixels();
+        if self.px_count != pixels {
+            return Err(EncodeError {
+                kind: EncodeErrorKind::DataDoesNotMatchDimensions {
+                    expected: pixels * self.desc.channels as usize,
+                    got: self.px_count * self.desc.channels as usize,
+                },
+            });
         }
 
-        px_prev = px;
+        self.flush_run()?;
+        self.writer.write_all(&PADDING)?;
+        Ok(self.writer)
+    }
+
+    fn pixels(&self) -> usize {
+        self.desc.width as usize * self.desc.height as usize
     }
 
-    crate::io::write(&mut bytes, &PADDING)?;
+    fn flush_run(&mut self) -> Result<(), EncodeError> {
+        if self.run > 0 {
+            self.writer.write_all(&[OP_RUN | (self.run - 1)])?;
+            self.run = 0;
+        }
 
-    let result = bytes.into_inner();
-    Ok(result)
+        Ok(())
+    }
 }
 
-pub fn decode(data: &[u8], channels: Option<Channels>) -> Result<(Vec<u8>, QoiDesc), DecodeError> {
-    if data.len() < DATA_MIN {
+/// parses only the header, without decoding the pixels. useful to get the dimensions of an image
+/// before decoding it.
+pub fn parse_header(data: &[u8]) -> Result<QoiDesc, DecodeError> {
+    if data.len() < HEADER_SIZE as usize {
         return Err(DecodeError {
             kind: DecodeErrorKind::DataToSmall,
         });
@@ -339,13 +426,47 @@ pub fn decode(data: &[u8], channels: Option<Channels>) -> Result<(Vec<u8>, QoiDe
         color_space: crate::io::read_u8(bytes)?.try_into()?,
     };
 
+    Ok(desc)
+}
+
+pub fn decode(data: &[u8], channels: Option<Channels>) -> Result<(Vec<u8>, QoiDesc), DecodeError> {
+    let mut pixels = Vec::new();
+    let desc = decode_into(data, channels, &mut pixels)?;
+    Ok((pixels, desc))
+}
+
+/// same as `decode`, but clears `pixels` and writes the decoded pixels into it, instead of
+/// allocating a new buffer. this allows to reuse a buffer when decoding many images.
+pub fn decode_into(
+    data: &[u8],
+    channels: Option<Channels>,
+    pixels: &mut Vec<u8>,
+) -> Result<QoiDesc, DecodeError> {
+    if data.len() < DATA_MIN {
+        return Err(DecodeError {
+            kind: DecodeErrorKind::DataToSmall,
+        });
+    }
+
+    let desc = parse_header(data)?;
+
+    if !data.ends_with(&PADDING) {
+        return Err(DecodeError {
+            kind: DecodeErrorKind::MissingEndMarker,
+        });
+    }
+
+    let bytes = &mut Cursor::new(data);
+    bytes.set_position(HEADER_SIZE as u64);
+
     let channels = match channels {
         Some(x) => x,
         None => desc.channels,
     };
 
     let px_len = desc.width as usize * desc.height as usize * channels as usize;
-    let mut pixels = Cursor::new(Vec::with_capacity(px_len));
+    pixels.clear();
+    pixels.reserve(px_len);
 
     let mut index = [Rgba::default(); 64];
     let mut px = Rgba::from_bytes(&[0, 0, 0, 255]);
@@ -388,15 +509,457 @@ pub fn decode(data: &[u8], channels: Option<Channels>) -> Result<(Vec<u8>, QoiDe
 
             let index_pos = px.hash() % 64;
             index[index_pos as usize] = px;
+        } else {
+            return Err(DecodeError {
+                kind: DecodeErrorKind::UnexpectedEof,
+            });
         }
 
-        crate::io::write(&mut pixels, &[px.r, px.g, px.b])?;
+        pixels.extend_from_slice(&[px.r, px.g, px.b]);
 
         if channels == Channels::RGBA {
-            crate::io::write(&mut pixels, &[px.a])?;
+            pixels.push(px.a);
+        }
+    }
+
+    Ok(desc)
+}
+
+/// reads and decodes the qoi file at `path`
+pub fn read_file(path: impl AsRef<Path>) -> Result<(Vec<u8>, QoiDesc), Box<dyn std::error::Error>> {
+    let bytes = std::fs::read(path)?;
+    let result = decode(&bytes, None)?;
+    Ok(result)
+}
+
+/// encodes `data` and writes it to `path`. missing directories are created, and an existing file
+/// is replaced.
+pub fn write_file(
+    path: impl AsRef<Path>,
+    data: &[u8],
+    desc: QoiDesc,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let path = path.as_ref();
+    let bytes = encode(data, desc)?;
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+
+    let mut file = std::fs::File::create_new(path)?;
+    crate::io::write(&mut file, &bytes)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::rng::Rng;
+    use crate::rng::Seed;
+
+    // generates an image that exercises every chunk: runs of up to 100 pixels, small and medium
+    // differences, random colors, colors that were seen before and, for rgba, changing alpha
+    fn generate_image(seed: u128, width: u32, height: u32, channels: Channels) -> Vec<u8> {
+        let mut rng = Rng::new(Seed(seed));
+        let count = width as usize * height as usize;
+
+        let mut result = Vec::with_capacity(count * channels as usize);
+        let mut seen = Vec::<[u8; 4]>::new();
+        let mut px = [0u8, 0, 0, 255];
+        let mut run = 0;
+
+        for _ in 0..count {
+            if run > 0 {
+                run -= 1;
+            } else {
+                match rng.next_i32_between(0, 5) {
+                    0 => run = rng.next_i32_between(1, 100),
+                    1 => {
+                        for c in px.iter_mut().take(3) {
+                            *c = c.wrapping_add(rng.next_i32_between(-2, 1) as u8);
+                        }
+                    }
+                    2 => {
+                        let vg = rng.next_i32_between(-32, 31);
+                        for c in px.iter_mut().take(3) {
+                            *c = c.wrapping_add((vg + rng.next_i32_between(-8, 7)) as u8);
+                        }
+                    }
+                    3 => {
+                        for c in px.iter_mut().take(3) {
+                            *c = rng.next_u8();
+                        }
+                    }
+                    4 if !seen.is_empty() => px = *rng.next_in(&seen),
+                    _ => px[3] = rng.next_u8(),
+                }
+
+                seen.push(px);
+            }
+
+            result.extend_from_slice(&px[..channels as usize]);
+        }
+
+        result
+    }
+
+    // counts the chunks of an encoded image, in the order RGB, RGBA, INDEX, DIFF, LUMA, RUN
+    fn count_chunks(qoi: &[u8]) -> [usize; 6] {
+        let mut counts = [0; 6];
+
+        let mut i = HEADER_SIZE as usize;
+        while i < qoi.len() - PADDING.len() {
+            let b1 = qoi[i];
+            let (kind, len) = if b1 == OP_RGB {
+                (0, 4)
+            } else if b1 == OP_RGBA {
+                (1, 5)
+            } else {
+                match b1 & MASK_2 {
+                    OP_INDEX => (2, 1),
+                    OP_DIFF => (3, 1),
+                    OP_LUMA => (4, 2),
+                    _ => (5, 1),
+                }
+            };
+
+            counts[kind] += 1;
+            i += len;
+        }
+
+        counts
+    }
+
+    fn desc(width: u32, height: u32, channels: Channels) -> QoiDesc {
+        QoiDesc {
+            width,
+            height,
+            channels,
+            color_space: ColorSpace::SRGB,
         }
     }
 
-    let result = pixels.into_inner();
-    Ok((result, desc))
+    #[test]
+    fn decode_round_trip() {
+        let mut total = [0; 6];
+
+        for (seed, width, height, channels) in [
+            (1, 1, 1, Channels::RGB),
+            (2, 16, 16, Channels::RGB),
+            (3, 37, 11, Channels::RGB),
+            (4, 64, 64, Channels::RGBA),
+            (5, 5, 97, Channels::RGBA),
+        ] {
+            let data = generate_image(seed, width, height, channels);
+            let desc = desc(width, height, channels);
+
+            let qoi = encode(&data, desc).unwrap();
+            let counts = count_chunks(&qoi);
+            for (t, c) in total.iter_mut().zip(counts) {
+                *t += c;
+            }
+
+            let (decoded, decoded_desc) = decode(&qoi, None).unwrap();
+            assert_eq!(decoded_desc, desc);
+            assert_eq!(decoded, data);
+        }
+
+        // every kind of chunk was decoded at least once
+        assert!(total.iter().all(|&x| x > 0), "{:?}", total);
+    }
+
+    #[test]
+    fn decode_without_end_marker() {
+        let data = generate_image(6, 32, 32, Channels::RGB);
+        let qoi = encode(&data, desc(32, 32, Channels::RGB)).unwrap();
+
+        let truncated = &qoi[..qoi.len() - PADDING.len()];
+        let error = decode(truncated, None).unwrap_err();
+        assert!(matches!(error.kind, DecodeErrorKind::MissingEndMarker));
+    }
+
+    #[test]
+    fn rgba_with_varying_alpha_round_trip() {
+        let (width, height) = (4, 3);
+        let mut data = Vec::new();
+        for i in 0..width * height {
+            let v = (i * 20) as u8;
+            data.extend_from_slice(&[v, 255 - v, 128, v.wrapping_mul(7)]);
+        }
+
+        let qoi = encode(&data, desc(width, height, Channels::RGBA)).unwrap();
+        assert!(count_chunks(&qoi)[1] > 0);
+
+        let (decoded, _) = decode(&qoi, None).unwrap();
+        assert_eq!(decoded, data);
+    }
+
+    #[test]
+    fn encode_too_short_data() {
+        let data = vec![0; 4 * 4 * 3 - 1];
+        let error = encode(&data, desc(4, 4, Channels::RGB)).unwrap_err();
+
+        let EncodeErrorKind::DataDoesNotMatchDimensions { expected, got } = error.kind else {
+            panic!("unexpected error: {:?}", error);
+        };
+        assert_eq!(expected, 48);
+        assert_eq!(got, 47);
+    }
+
+    // the encoder before `QoiEncoder` existed. it flushes the last run on the last pixel, instead
+    // of in `finish`. kept to check that the streaming encoder produces the same bytes
+    fn reference_encode(data: &[u8], desc: QoiDesc) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&MAGIC);
+        bytes.extend_from_slice(&desc.width.to_be_bytes());
+        bytes.extend_from_slice(&desc.height.to_be_bytes());
+        bytes.extend_from_slice(&[desc.channels as u8, desc.color_space as u8]);
+
+        let mut index = [Rgba::default(); 64];
+
+        let mut run = 0;
+        let mut px_prev = Rgba::from_bytes(&[0, 0, 0, 255]);
+        let mut px = px_prev;
+
+        let px_len = data.len();
+        let px_end = px_len - desc.channels as usize;
+        let channels = desc.channels as usize;
+
+        for px_pos in (0..px_len).step_by(channels) {
+            px.r = data[px_pos];
+            px.g = data[px_pos + 1];
+            px.b = data[px_pos + 2];
+
+            if channels == 4 {
+                px.a = data[px_pos + 3];
+            }
+
+            if px == px_prev {
+                run += 1;
+                if run == 62 || px_pos == px_end {
+                    bytes.push(OP_RUN | (run - 1));
+                    run = 0;
+                }
+            } else {
+                if run > 0 {
+                    bytes.push(OP_RUN | (run - 1));
+                    run = 0;
+                }
+
+                let index_pos = px.hash() % 64;
+
+                if index[index_pos as usize] == px {
+                    bytes.push(OP_INDEX | index_pos);
+                } else {
+                    index[index_pos as usize] = px;
+
+                    if px.a == px_prev.a {
+                        let vr = (px.r as i8).wrapping_sub(px_prev.r as i8);
+                        let vg = (px.g as i8).wrapping_sub(px_prev.g as i8);
+                        let vb = (px.b as i8).wrapping_sub(px_prev.b as i8);
+
+                        let vg_r = vr.wrapping_sub(vg);
+                        let vg_b = vb.wrapping_sub(vg);
+
+                        if vr > -3 && vr < 2 && vg > -3 && vg < 2 && vb > -3 && vb < 2 {
+                            let dr = ((vr + 2) << 4) as u8;
+                            let dg = ((vg + 2) << 2) as u8;
+                            let db = (vb + 2) as u8;
+                            bytes.push(OP_DIFF | dr | dg | db);
+                        } else if vg_r > -9
+                            && vg_r < 8
+                            && vg > -33
+                            && vg < 32
+                            && vg_b > -9
+                            && vg_b < 8
+                        {
+                            let dr = ((vg_r + 8) << 4) as u8;
+                            let dg = (vg + 32) as u8;
+                            let db = (vg_b + 8) as u8;
+                            bytes.extend_from_slice(&[OP_LUMA | dg, dr | db]);
+                        } else {
+                            bytes.extend_from_slice(&[OP_RGB, px.r, px.g, px.b]);
+                        }
+                    } else {
+                        bytes.extend_from_slice(&[OP_RGBA, px.r, px.g, px.b, px.a]);
+                    }
+                }
+            }
+
+            px_prev = px;
+        }
+
+        bytes.extend_from_slice(&PADDING);
+        bytes
+    }
+
+    // overwrites `len` pixels starting at pixel `start` with the pixel before, to form a run
+    fn insert_run(data: &mut [u8], channels: Channels, start: usize, len: usize) {
+        let c = channels as usize;
+        for i in start..start + len {
+            data.copy_within((start - 1) * c..start * c, i * c);
+        }
+    }
+
+    #[test]
+    fn streaming_encoder_matches_encode() {
+        for (seed, width, height, channels) in [
+            (7, 1, 1, Channels::RGB),
+            (8, 16, 16, Channels::RGB),
+            (9, 33, 17, Channels::RGB),
+            (10, 16, 16, Channels::RGBA),
+            (11, 41, 9, Channels::RGBA),
+        ] {
+            let count = width as usize * height as usize;
+            let mut data = generate_image(seed, width, height, channels);
+            if count > 200 {
+                // a run longer than 62 pixels in the middle, and a run that ends the image
+                insert_run(&mut data, channels, 50, 130);
+                insert_run(&mut data, channels, count - 70, 70);
+            }
+            let desc = desc(width, height, channels);
+
+            let mut encoder = QoiEncoder::new(Vec::new(), desc).unwrap();
+            for px in data.chunks_exact(channels as usize) {
+                let a = px.get(3).copied().unwrap_or(255);
+                encoder.push_pixel([px[0], px[1], px[2], a]).unwrap();
+            }
+            let streamed = encoder.finish().unwrap();
+
+            let encoded = encode(&data, desc).unwrap();
+            assert_eq!(streamed, encoded);
+            assert_eq!(encoded, reference_encode(&data, desc));
+        }
+    }
+
+    #[test]
+    fn streaming_encoder_rejects_wrong_pixel_count() {
+        let desc = desc(2, 2, Channels::RGBA);
+
+        let mut encoder = QoiEncoder::new(Vec::new(), desc).unwrap();
+        for _ in 0..4 {
+            encoder.push_pixel([1, 2, 3, 4]).unwrap();
+        }
+        let error = encoder.push_pixel([1, 2, 3, 4]).unwrap_err();
+        assert!(matches!(
+            error.kind,
+            EncodeErrorKind::DataDoesNotMatchDimensions {
+                expected: 16,
+                got: 20
+            }
+        ));
+
+        let mut encoder = QoiEncoder::new(Vec::new(), desc).unwrap();
+        for _ in 0..3 {
+            encoder.push_pixel([1, 2, 3, 4]).unwrap();
+        }
+        let error = encoder.finish().unwrap_err();
+        assert!(matches!(
+            error.kind,
+            EncodeErrorKind::DataDoesNotMatchDimensions {
+                expected: 16,
+                got: 12
+            }
+        ));
+    }
+
+    #[test]
+    fn file_round_trip() {
+        let data = generate_image(12, 20, 10, Channels::RGBA);
+        let desc = desc(20, 10, Channels::RGBA);
+
+        let path = std::env::temp_dir()
+            .join(format!("ris_terrain_generator_{}", std::process::id()))
+            .join("qoi_round_trip.qoi");
+        write_file(&path, &data, desc).unwrap();
+        let (read_data, read_desc) = read_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(read_desc, desc);
+        assert_eq!(read_data, data);
+    }
+
+    #[test]
+    fn decode_corrupt_data() {
+        let data = generate_image(13, 32, 32, Channels::RGB);
+        let qoi = encode(&data, desc(32, 32, Channels::RGB)).unwrap();
+
+        // chunks cut short, but the end marker is still present
+        let chunks_end = qoi.len() - PADDING.len();
+        let mut truncated = qoi[..chunks_end / 2].to_vec();
+        truncated.extend_from_slice(&PADDING);
+        let error = decode(&truncated, None).unwrap_err();
+        assert!(matches!(error.kind, DecodeErrorKind::UnexpectedEof));
+
+        let mut bad_magic = qoi.clone();
+        bad_magic[0] = b'x';
+        let error = decode(&bad_magic, None).unwrap_err();
+        assert!(matches!(error.kind, DecodeErrorKind::IncorrectMagic));
+
+        let mut zero_width = qoi.clone();
+        zero_width[4..8].copy_from_slice(&0u32.to_be_bytes());
+        let error = decode(&zero_width, None).unwrap_err();
+        assert!(matches!(error.kind, DecodeErrorKind::DescWidthIsZero));
+    }
+
+    #[test]
+    fn parse_header_of_valid_image() {
+        let desc = QoiDesc {
+            width: 7,
+            height: 3,
+            channels: Channels::RGBA,
+            color_space: ColorSpace::Linear,
+        };
+        let qoi = encode(&generate_image(14, 7, 3, Channels::RGBA), desc).unwrap();
+
+        let parsed = parse_header(&qoi).unwrap();
+        assert_eq!(parsed.width, 7);
+        assert_eq!(parsed.height, 3);
+        assert_eq!(parsed.channels, Channels::RGBA);
+        assert_eq!(parsed, desc);
+
+        // the header alone is enough
+        assert_eq!(parse_header(&qoi[..HEADER_SIZE as usize]).unwrap(), desc);
+    }
+
+    #[test]
+    fn parse_header_errors() {
+        let qoi = encode(&[0; 3], desc(1, 1, Channels::RGB)).unwrap();
+
+        let mut bad_magic = qoi.clone();
+        bad_magic[3] = b'g';
+        let error = parse_header(&bad_magic).unwrap_err();
+        assert!(matches!(error.kind, DecodeErrorKind::IncorrectMagic));
+
+        let error = parse_header(&qoi[..HEADER_SIZE as usize - 1]).unwrap_err();
+        assert!(matches!(error.kind, DecodeErrorKind::DataToSmall));
+    }
+
+    #[test]
+    fn decode_into_reused_buffer() {
+        let large = encode(
+            &generate_image(15, 30, 20, Channels::RGBA),
+            desc(30, 20, Channels::RGBA),
+        )
+        .unwrap();
+        let small = encode(
+            &generate_image(16, 5, 4, Channels::RGB),
+            desc(5, 4, Channels::RGB),
+        )
+        .unwrap();
+
+        let mut pixels = Vec::new();
+        for qoi in [&large, &small, &large] {
+            let desc = decode_into(qoi, None, &mut pixels).unwrap();
+            let (expected, expected_desc) = decode(qoi, None).unwrap();
+            assert_eq!(desc, expected_desc);
+            assert_eq!(pixels, expected);
+        }
+    }
 }
@@ -1,3 +1,7 @@
+use std::f32::consts::FRAC_PI_2;
+use std::f32::consts::PI;
+
+use crate::matrix::Mat3;
 use crate::vector::Vec3;
 use crate::vector::Vec4;
 
@@ -28,6 +32,43 @@ impl Quat {
 
         Self(n.0 * im, n.1 * im, n.2 * im, re)
     }
+
+    /// creates a rotation from euler angles in radians. `yaw` rotates around z, `pitch` around y
+    /// and `roll` around x. the angles are intrinsic and applied in the order yaw, pitch, roll,
+    /// i.e. the result is `yaw * pitch * roll`. this is the inverse of `to_euler`.
+    pub fn from_euler(yaw: f32, pitch: f32, roll: f32) -> Self {
+        let (sy, cy) = f32::sin_cos(yaw * 0.5);
+        let (sp, cp) = f32::sin_cos(pitch * 0.5);
+        let (sr, cr) = f32::sin_cos(roll * 0.5);
+
+        Self(
+            sr * cp * cy - cr * sp * sy,
+            cr * sp * cy + sr * cp * sy,
+            cr * cp * sy - sr * sp * cy,
+            cr * cp * cy + sr * sp * sy,
+        )
+    }
+
+    /// returns the rotation that turns +z into `forward` and +y towards `up`. `forward` and `up`
+    /// do not need to be normalized or perpendicular. if they are parallel, another up is chosen.
+    pub fn look_rotation(forward: Vec3, up: Vec3) -> Self {
+        let z = forward.normalize();
+
+        let mut x = up.cross(z);
+        if x.length_squared() < 0.000_001 {
+            // up is parallel to forward, any perpendicular axis will do
+            let fallback = if z.0.abs() < 0.9 {
+                Vec3(1., 0., 0.)
+            } else {
+                Vec3(0., 1., 0.)
+            };
+            x = fallback.cross(z);
+        }
+        let x = x.normalize();
+        let y = z.cross(x);
+
+        Mat3(x, y, z).to_quat()
+    }
 }
 
 impl Default for Quat {
@@ -200,6 +241,13 @@ impl Quat {
         Self(-self.0, -self.1, -self.2, self.3)
     }
 
+    /// returns the quaternion that undoes the rotation of `self`. for unit quaternions, this is
+    /// the same as `conjugate`.
+    pub fn inverse(self) -> Self {
+        let c = Vec4::from(self.conjugate());
+        Quat::from(c / self.length_squared())
+    }
+
     pub fn normalize(self) -> Self {
         let q_ = Vec4::from(self);
         Quat::from(q_.normalize())
@@ -215,6 +263,64 @@ impl Quat {
         q_.length()
     }
 
+    /// returns the euler angles `(yaw, pitch, roll)` in radians, as they are expected by
+    /// `from_euler`. `self` is expected to be normalized. pitch is in [-PI/2, PI/2], yaw and roll
+    /// are in [-PI, PI].
+    ///
+    /// at a pitch of +-PI/2 (gimbal lock) yaw and roll rotate around the same axis and cannot be
+    /// told apart. in that case roll is 0, and the whole rotation around that axis is returned as
+    /// yaw.
+    pub fn to_euler(self) -> (f32, f32, f32) {
+        let Quat(x, y, z, w) = self;
+
+        let sin_pitch = 2. * (w * y - z * x);
+        if sin_pitch.abs() > 0.999_99 {
+            let pitch = f32::copysign(FRAC_PI_2, sin_pitch);
+            let mut yaw = 2. * f32::atan2(z, w);
+            if yaw > PI {
+                yaw -= 2. * PI;
+            } else if yaw < -PI {
+                yaw += 2. * PI;
+            }
+            return (yaw, pitch, 0.);
+        }
+
+        let yaw = f32::atan2(2. * (w * z + x * y), 1. - 2. * (y * y + z * z));
+        let pitch = f32::asin(sin_pitch);
+        let roll = f32::atan2(2. * (w * x + y * z), 1. - 2. * (x * x + y * y));
+
+        (yaw, pitch, roll)
+    }
+
+    // interpolation
+
+    /// spherical linear interpolation from `a` to `b`, taking the shortest path. `a` and `b` are
+    /// expected to be normalized. if they are nearly parallel, falls back to a normalized linear
+    /// interpolation, to avoid dividing by a sine close to zero.
+    pub fn slerp(a: Self, b: Self, t: f32) -> Self {
+        let a_ = Vec4::from(a);
+        let mut b_ = Vec4::from(b);
+
+        // q and -q represent the same rotation. flip b, such that the shorter arc is taken
+        let mut dot = a_.dot(b_);
+        if dot < 0. {
+            b_ = -b_;
+            dot = -dot;
+        }
+
+        if dot > 0.9995 {
+            let r = a_ + (b_ - a_) * t;
+            return Quat::from(r.normalize());
+        }
+
+        let theta = f32::acos(dot);
+        let sin_theta = f32::sin(theta);
+        let wa = f32::sin((1. - t) * theta) / sin_theta;
+        let wb = f32::sin(t * theta) / sin_theta;
+
+        Quat::from(a_ * wa + b_ * wb)
+    }
+
     // 3d functions
     pub fn rotate(self, p: Vec3) -> Vec3 {
         let r = self;
@@ -225,9 +331,31 @@ impl Quat {
 
         Vec3(p_.0, p_.1, p_.2)
     }
+
+    /// returns the rotation matrix of self. same as `Mat3::from_quat(self)`.
+    pub fn to_mat3(self) -> Mat3 {
+        Mat3::from_quat(self)
+    }
+
+    /// rotates all vectors in place. equivalent to calling `rotate` for each vector, but the
+    /// rotation matrix is only computed once.
+    pub fn rotate_slice(self, vs: &mut [Vec3]) {
+        let m = Mat3(
+            self.rotate(Vec3(1., 0., 0.)),
+            self.rotate(Vec3(0., 1., 0.)),
+            self.rotate(Vec3(0., 0., 1.)),
+        );
+
+        for v in vs.iter_mut() {
+            *v = m * *v;
+        }
+    }
 }
 
 // Hamilton Product: https://en.wikipedia.org/wiki/Quaternion#Hamilton_product
+//
+// the product of two unit quaternions is a unit quaternion, but rounding errors accumulate when
+// many products are chained. `normalize` the result of long chains before using it to rotate.
 impl std::ops::Mul<Quat> for Quat {
     type Output = Self;
 
@@ -254,3 +382,103 @@ impl std::cmp::PartialEq for Quat {
 }
 
 impl std::cmp::Eq for Quat {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // q and -q represent the same rotation
+    fn same_rotation(a: Quat, b: Quat) -> bool {
+        let a = Vec4::from(a);
+        let b = Vec4::from(b);
+        a.approx_eq(b, 0.000_1) || a.approx_eq(-b, 0.000_1)
+    }
+
+    #[test]
+    fn slerp_hits_endpoints_and_stays_normalized() {
+        let a = Quat::angle_axis(0.3, Vec3(0., 0., 1.));
+        let b = Quat::angle_axis(2.5, Vec3(1., 1., 0.));
+
+        assert!(same_rotation(Quat::slerp(a, b, 0.), a));
+        assert!(same_rotation(Quat::slerp(a, b, 1.), b));
+
+        let half = Quat::slerp(a, b, 0.5);
+        assert!((half.length() - 1.).abs() < 0.000_01);
+    }
+
+    #[test]
+    fn euler_round_trip() {
+        for (yaw, pitch, roll) in [
+            (0., 0., 0.),
+            (0.5, 0., 0.),
+            (0., -0.7, 0.),
+            (0., 0., 1.2),
+            (1.0, 0.4, -2.0),
+            (-3.0, -1.3, 0.1),
+        ] {
+            let (yaw_, pitch_, roll_) = Quat::from_euler(yaw, pitch, roll).to_euler();
+            assert!((yaw - yaw_).abs() < 0.000_1, "{} {}", yaw, yaw_);
+            assert!((pitch - pitch_).abs() < 0.000_1, "{} {}", pitch, pitch_);
+            assert!((roll - roll_).abs() < 0.000_1, "{} {}", roll, roll_);
+        }
+    }
+
+    #[test]
+    fn euler_gimbal_lock_keeps_rotation() {
+        let q = Quat::from_euler(0.3, FRAC_PI_2, 0.5);
+        let (yaw, pitch, roll) = q.to_euler();
+
+        assert_eq!(roll, 0.);
+        assert!(same_rotation(Quat::from_euler(yaw, pitch, roll), q));
+    }
+
+    #[test]
+    fn normalize_yields_unit_rotation() {
+        let q = Quat(1., -2., 3., 0.5).normalize();
+        assert!((q.length() - 1.).abs() < 0.000_01);
+
+        let v = Vec3(3., -1., 2.);
+        assert!((q.rotate(v).length() - v.length()).abs() < 0.000_1);
+    }
+
+    #[test]
+    fn inverse_undoes_rotation() {
+        let v = Vec3(0.5, 2., -1.);
+        for q in [
+            Quat::angle_axis(1.1, Vec3(1., 2., 3.)),
+            // not normalized, inverse must still undo it
+            Quat(0.2, -0.4, 0.1, 2.),
+        ] {
+            assert!(q.rotate(q.inverse().rotate(v)).approx_eq(v, 0.000_1));
+            assert!(same_rotation(q * q.inverse(), Quat::identity()));
+        }
+    }
+
+    #[test]
+    fn mat3_round_trip() {
+        let mut rng = crate::rng::Rng::new(crate::rng::Seed(3));
+        for _ in 0..1000 {
+            let q = rng.next_rot();
+            assert!(same_rotation(q.to_mat3().to_quat(), q), "{:?}", q);
+        }
+    }
+
+    #[test]
+    fn look_rotation_maps_z_to_forward() {
+        for (forward, up) in [
+            (Vec3(1., 2., 3.), Vec3(0., 1., 0.)),
+            (Vec3(0., 0., -5.), Vec3(0., 1., 0.)),
+            // parallel to up
+            (Vec3(0., 2., 0.), Vec3(0., 1., 0.)),
+        ] {
+            let q = Quat::look_rotation(forward, up);
+            let z = q.rotate(Vec3(0., 0., 1.));
+            assert!(
+                z.approx_eq(forward.normalize(), 0.000_1),
+                "{:?} {:?}",
+                z,
+                forward
+            );
+        }
+    }
+}
@@ -48,18 +48,47 @@ impl Seed {
     pub fn zero() -> Self {
         Seed(0)
     }
+
+    /// hashes `bytes` into a seed, using 128 bit FNV-1a: https://en.wikipedia.org/wiki/Fowler%E2%80%93Noll%E2%80%93Vo_hash_function
+    ///
+    /// the hash does not depend on the platform or on the run, thus equal bytes always result in
+    /// equal seeds.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        const OFFSET_BASIS: u128 = 0x6c62272e07bb014262b821756295c58d;
+        const PRIME: u128 = 0x0000000001000000000000000000013b;
+
+        let mut hash = OFFSET_BASIS;
+        for &byte in bytes {
+            hash ^= byte as u128;
+            hash = hash.wrapping_mul(PRIME);
+        }
+
+        Self(hash)
+    }
+
+    /// hashes the UTF-8 bytes of `s` into a seed, see `from_bytes`. useful to name worlds, like
+    /// `Seed::from_str("atlantis")`. equal strings always result in equal seeds.
+    pub fn from_str(s: &str) -> Self {
+        Self::from_bytes(s.as_bytes())
+    }
 }
 
 #[derive(Debug)]
 pub struct Rng {
     seed: Seed,
     pcg: Pcg32,
+    // box-muller produces two samples at once. the second one is kept for the next call
+    spare_gaussian: Option<f32>,
 }
 
 impl Rng {
     pub fn new(seed: Seed) -> Rng {
         let pcg = Pcg32::new_from_seed(seed.0);
-        let mut result = Rng { seed, pcg };
+        let mut result = Rng {
+            seed,
+            pcg,
+            spare_gaussian: None,
+        };
         result.skip(128);
         result
     }
@@ -68,6 +97,14 @@ impl Rng {
         &self.seed
     }
 
+    /// returns a new generator, which is seeded by the next 128 bits of self. the child is
+    /// independent of self afterwards: drawing from one does not change the sequence of the other.
+    /// thus, forking the same parent state always results in the same child, and forking twice in
+    /// a row results in two different children.
+    pub fn fork(&mut self) -> Rng {
+        Rng::new(Seed(self.next_u128()))
+    }
+
     // advance internal state n times. useful for warming up the generator
     pub fn skip(&mut self, n: usize) {
         for _ in 0..n {
@@ -124,6 +161,12 @@ impl Rng {
         (self.next_u32() & 1) == 1
     }
 
+    /// returns true with the probability `p`. `p` is clamped to [0.0, 1.0], thus 0.0 never and
+    /// 1.0 always returns true
+    pub fn next_bool_with_probability(&mut self, p: f32) -> bool {
+        self.next_f32() < f32::clamp(p, 0.0, 1.0)
+    }
+
     /// returns a random u8
     pub fn next_u8(&mut self) -> u8 {
         (self.next_u32() & 0xFF) as u8
@@ -144,7 +187,8 @@ impl Rng {
         f32::from_bits(0x3F80_0000 | (value & 0x7F_FFFF)) - 1.0
     }
 
-    /// returns a random f32 between 0.0 and 1.0
+    /// returns a random f32 in [0.0, 1.0). 1.0 is never returned, because the result is built
+    /// from 23 random mantissa bits, see `hash_to_f32`
     pub fn next_f32(&mut self) -> f32 {
         Self::hash_to_f32(self.next_u32())
     }
@@ -168,6 +212,24 @@ impl Rng {
         }
     }
 
+    /// returns a normally distributed f32 with the given mean and standard deviation, using the
+    /// box-muller transform: https://en.wikipedia.org/wiki/Box%E2%80%93Muller_transform
+    pub fn next_gaussian(&mut self, mean: f32, std_dev: f32) -> f32 {
+        if let Some(z) = self.spare_gaussian.take() {
+            return mean + std_dev * z;
+        }
+
+        // u1 is in (0, 1], such that its logarithm is finite
+        let u1 = 1.0 - self.next_f32();
+        let u2 = self.next_f32();
+
+        let r = f32::sqrt(-2.0 * f32::ln(u1));
+        let (sin, cos) = f32::sin_cos(2.0 * PI * u2);
+        self.spare_gaussian = Some(r * sin);
+
+        mean + std_dev * r * cos
+    }
+
     /// min and max are inclusive
     pub fn next_i32_between(&mut self, min: i32, max: i32) -> i32 {
         let max = max + 1;
@@ -196,6 +258,15 @@ impl Rng {
         &slice[index]
     }
 
+    /// shuffles `slice` in place, such that every permutation is equally likely, using the
+    /// fisher-yates shuffle: https://en.wikipedia.org/wiki/Fisher%E2%80%93Yates_shuffle
+    pub fn shuffle<T>(&mut self, slice: &mut [T]) {
+        for i in (1..slice.len()).rev() {
+            let j = self.next_i32_between(0, i as i32) as usize;
+            slice.swap(i, j);
+        }
+    }
+
     pub fn next_pos_2(&mut self) -> Vec2 {
         let x = self.next_f32_between(-1.0, 1.0);
         let y = self.next_f32_between(-1.0, 1.0);
@@ -220,8 +291,11 @@ impl Rng {
         Vec4(x, y, z, w)
     }
 
+    /// returns a random unit vector. unlike normalizing `next_pos_2`, which favors the diagonals of
+    /// the square, the angle is uniformly distributed
     pub fn next_dir_2(&mut self) -> Vec2 {
-        self.next_pos_2().normalize()
+        let angle = self.next_f32_between(0.0, 2.0 * PI);
+        Vec2::from_angle(angle)
     }
 
     pub fn next_dir_3(&mut self) -> Vec3 {
@@ -258,3 +332,132 @@ impl Rng {
         OkLch(l, c, h)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLES: usize = 100_000;
+
+    #[test]
+    fn next_f32_is_uniform_in_range() {
+        let mut rng = Rng::new(Seed(1));
+
+        let mut sum = 0.0;
+        for _ in 0..SAMPLES {
+            let x = rng.next_f32();
+            assert!((0.0..1.0).contains(&x), "{}", x);
+            sum += x as f64;
+        }
+        let mean = sum / SAMPLES as f64;
+        assert!((mean - 0.5).abs() < 0.01, "{}", mean);
+
+        for _ in 0..SAMPLES {
+            let x = rng.next_f32_between(-3.0, 5.0);
+            assert!((-3.0..=5.0).contains(&x), "{}", x);
+        }
+        assert_eq!(rng.next_f32_between(2.0, 2.0), 2.0);
+    }
+
+    #[test]
+    fn seed_from_str() {
+        assert_eq!(Seed::from_str("atlantis"), Seed::from_str("atlantis"));
+        assert_ne!(Seed::from_str("atlantis"), Seed::from_str("lemuria"));
+        assert_ne!(Seed::from_str("ab"), Seed::from_str("ba"));
+        assert_eq!(Seed::from_str("atlantis"), Seed::from_bytes(b"atlantis"));
+
+        // the empty input hashes to the fnv offset basis
+        assert_eq!(Seed::from_str(""), Seed(0x6c62272e07bb014262b821756295c58d));
+    }
+
+    #[test]
+    fn next_gaussian_mean_and_std_dev() {
+        let mut rng = Rng::new(Seed(2));
+
+        let samples = (0..SAMPLES)
+            .map(|_| rng.next_gaussian(3.0, 2.0) as f64)
+            .collect::<Vec<_>>();
+        let mean = samples.iter().sum::<f64>() / SAMPLES as f64;
+        let variance =
+            samples.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / SAMPLES as f64;
+
+        assert!((mean - 3.0).abs() < 0.05, "{}", mean);
+        assert!((variance.sqrt() - 2.0).abs() < 0.05, "{}", variance.sqrt());
+    }
+
+    #[test]
+    fn next_dir_2_is_unit_and_uniform() {
+        const BINS: usize = 8;
+
+        let mut rng = Rng::new(Seed(3));
+        let mut bins = [0usize; BINS];
+        for _ in 0..SAMPLES {
+            let v = rng.next_dir_2();
+            assert!((v.length() - 1.0).abs() < 0.000_01);
+
+            let turns = (v.angle() + PI) / (2.0 * PI);
+            let bin = usize::min((turns * BINS as f32) as usize, BINS - 1);
+            bins[bin] += 1;
+        }
+
+        let expected = SAMPLES / BINS;
+        for count in bins {
+            assert!(count.abs_diff(expected) < expected / 20, "{:?}", bins);
+        }
+    }
+
+    #[test]
+    fn shuffle_is_deterministic_permutation() {
+        let original = (0..50).collect::<Vec<_>>();
+
+        let mut a = original.clone();
+        Rng::new(Seed(4)).shuffle(&mut a);
+        let mut b = original.clone();
+        Rng::new(Seed(4)).shuffle(&mut b);
+        assert_eq!(a, b);
+        assert_ne!(a, original);
+
+        a.sort();
+        assert_eq!(a, original);
+
+        // must not panic on trivial slices
+        let mut rng = Rng::new(Seed(4));
+        rng.shuffle::<i32>(&mut []);
+        rng.shuffle(&mut [1]);
+    }
+
+    #[test]
+    fn next_bool_with_probability_ratio() {
+        let mut rng = Rng::new(Seed(5));
+        for p in [0.1, 0.5, 0.75] {
+            let hits = (0..SAMPLES)
+                .filter(|_| rng.next_bool_with_probability(p))
+                .count();
+            let ratio = hits as f32 / SAMPLES as f32;
+            assert!((ratio - p).abs() < 0.01, "{} {}", ratio, p);
+        }
+
+        assert!((0..1000).all(|_| !rng.next_bool_with_probability(0.0)));
+        assert!((0..1000).all(|_| rng.next_bool_with_probability(1.0)));
+    }
+
+    #[test]
+    fn fork_is_deterministic_and_diverges() {
+        let mut parent_a = Rng::new(Seed(6));
+        let mut parent_b = Rng::new(Seed(6));
+
+        let mut child_a = parent_a.fork();
+        let mut child_b = parent_b.fork();
+        let a = (0..16).map(|_| child_a.next_u32()).collect::<Vec<_>>();
+        let b = (0..16).map(|_| child_b.next_u32()).collect::<Vec<_>>();
+        assert_eq!(a, b);
+
+        // forking twice yields a different child, and the parent continues independently
+        let mut sibling = parent_a.fork();
+        let s = (0..16).map(|_| sibling.next_u32()).collect::<Vec<_>>();
+        assert_ne!(a, s);
+        let p = (0..16).map(|_| parent_a.next_u32()).collect::<Vec<_>>();
+        assert_ne!(a, p);
+        assert_ne!(s, p);
+    }
+}
@@ -33,6 +33,8 @@ pub enum Side {
     D,
 }
 
+#[allow(clippy::derivable_impls)]
+// justification: the default is spelled out, instead of hiding it in an attribute on a variant
 impl Default for Side {
     fn default() -> Self {
         Self::L
@@ -53,7 +55,7 @@ impl std::fmt::Display for Side {
 }
 
 impl Side {
-    fn to_index(self) -> usize {
+    pub fn to_index(self) -> usize {
         match self {
             Side::L => 0,
             Side::B => 1,
@@ -80,6 +82,28 @@ impl From<usize> for Side {
     }
 }
 
+#[derive(Debug)]
+pub enum TerrainError {
+    /// the continents of the seed are clumped together or very unevenly sized. only returned if
+    /// `Args::reject_degenerate` is set.
+    DegenerateSeed,
+    /// `Args::continent_min_speed` is larger than `Args::continent_max_speed`.
+    InvalidSpeedRange,
+}
+
+impl std::fmt::Display for TerrainError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TerrainError::DegenerateSeed => write!(f, "the seed produces degenerate continents"),
+            TerrainError::InvalidSpeedRange => {
+                write!(f, "continent_min_speed is larger than continent_max_speed")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TerrainError {}
+
 #[allow(dead_code)]
 // justification: implementation exists, and client code may
 // choose the currently un-instantiated value in the future
@@ -88,6 +112,52 @@ pub enum ErosionKind {
     Rng,
 }
 
+#[allow(dead_code)]
+// justification: implementation exists, and client code may
+// choose the currently un-instantiated value in the future
+#[derive(Debug, Clone, Copy)]
+pub enum BoundaryStyle {
+    Sharp,
+    Smooth,
+}
+
+#[allow(dead_code)]
+// justification: implementation exists, and client code may
+// choose the currently un-instantiated value in the future
+#[derive(Debug, Clone, Copy)]
+pub enum BoundaryFalloff {
+    Linear,
+    Quadratic,
+    Circular,
+    Gaussian,
+}
+
+impl BoundaryFalloff {
+    // x is the normalized distance to the continental boundary, where 0 is on the boundary and 1
+    // is `continental_mountain_thickness` away from it. returns 1 on the boundary, and falls off
+    // to (roughly) 0 at x = 1
+    fn weight(self, x: f32) -> f32 {
+        let x = x.clamp(0.0, 1.0);
+        let x_ = 1.0 - x;
+
+        match self {
+            BoundaryFalloff::Linear => x_,
+            // https://www.desmos.com/calculator/4p8se0qln8
+            BoundaryFalloff::Quadratic => x_ * x_,
+            BoundaryFalloff::Circular => 1.0 - f32::sqrt(1.0 - x_ * x_),
+            BoundaryFalloff::Gaussian => f32::exp(-4.0 * x * x),
+        }
+    }
+}
+
+#[allow(dead_code)]
+// justification: implementation exists, and client code may
+// choose the currently un-instantiated value in the future
+pub enum NoiseKind {
+    Perlin,
+    Spectral,
+}
+
 pub struct Args {
     /// a wrapper around a `u128`, which controls the RNG of the generator. the same seed will
     /// produce the same terrain. `Seed::new()` generates a completely new and unique seed, which
@@ -103,15 +173,50 @@ pub struct Args {
     /// determines how many continents should be generated. these are used to generate very coarse
     /// terrain features, like mountain peaks and trenches.
     pub continent_count: usize,
+    /// biases which frontier pixel a continent grows into next. `0.0` picks uniformly, which
+    /// produces the classic blobby shapes. positive values up to `1.0` prefer pixels that lie
+    /// along the direction the continent is rotating in, producing elongated continents. negative
+    /// values down to `-1.0` prefer pixels close to the continent origin, producing compact
+    /// continents.
+    pub continent_growth_bias: f32,
+    /// every continent rotates with a random angular speed between `continent_min_speed` and
+    /// `continent_max_speed`. when two continents collide, their speeds determine how intense the
+    /// collision is, i.e. faster continents produce taller mountains and deeper trenches. set both
+    /// to the same value, for all continents to move at the same speed. `run` returns
+    /// `TerrainError::InvalidSpeedRange` if the min is larger than the max.
+    pub continent_min_speed: f32,
+    /// see `continent_min_speed`
+    pub continent_max_speed: f32,
     /// determines the width of the continental mountains/trenches. note that the height falls of
     /// with distance to the continental boundary, which may become smaller than the perlin noise,
     /// thus mountains/trenches may appear to be not as wide as one might expect from this setting.
     pub continental_mountain_thickness: usize,
+    /// determines how the height at continental boundaries is calculated. `BoundaryStyle::Sharp`
+    /// measures the movement of both continents relative to the origin of the continent, which
+    /// produces jagged and interesting mountain ranges. `BoundaryStyle::Smooth` measures the
+    /// movement relative to the boundary itself, which produces smoother, but in my opinion less
+    /// interesting terrain.
+    pub boundary_style: BoundaryStyle,
+    /// determines how the height falls off with distance to the continental boundary, and thus how
+    /// wide mountains/trenches spread. `BoundaryFalloff::Linear` produces wide, cone-like ranges.
+    /// `BoundaryFalloff::Quadratic` and `BoundaryFalloff::Circular` concentrate the height closer
+    /// to the boundary. `BoundaryFalloff::Gaussian` produces a plateau at the boundary, which
+    /// smoothly fades out.
+    pub boundary_falloff: BoundaryFalloff,
+    /// determines which noise is layered on top of the coarse continent terrain.
+    /// `NoiseKind::Perlin` sums multiple octaves of perlin noise, controlled by
+    /// `fractal_main_layer`. `NoiseKind::Spectral` synthesizes a gaussian random field, whose
+    /// power spectrum falls off with 1/f^β, controlled by `spectral_beta`.
+    pub noise_kind: NoiseKind,
     /// the main octave of the fractal perlin noise. every other octave will be weighted less than
     /// the main layer. the further away an octave is from the main one, the smaller its
     /// contribution to the noise.
     pub fractal_main_layer: usize,
-    /// the weight of the total fractal perlin noise. the coarse continent terrain has a weight of
+    /// the exponent β of the spectral noise. small values produce rough, almost white noise. large
+    /// values produce smooth, rolling hills. values around 2 resemble natural terrain. only has an
+    /// effect when `noise_kind` is `NoiseKind::Spectral`.
+    pub spectral_beta: f32,
+    /// the weight of the total noise. the coarse continent terrain has a weight of
     /// 1.
     pub fractal_weight: f32,
     /// determines how the position of the rain droplets should be found. `ErosionKind::Stride`
@@ -149,6 +254,57 @@ pub struct Args {
     pub erosion_gravity: f32,
     /// sets how much water the raindrop loses after each step.
     pub erosion_evaporate_speed: f32,
+    /// determines how many iterations the glacier simulation runs. glaciers form where it is
+    /// cold, i.e. close to the poles and on high terrain. they flow downhill and carve wide,
+    /// U-shaped valleys, as opposed to the V-shaped valleys that the raindrops produce. 0 disables
+    /// glaciers.
+    pub glacier_iterations: usize,
+    /// the temperature below which ice accumulates, and above which it melts. the temperature is
+    /// 1 at the equator and 0 at the poles, minus `glacier_lapse_rate` times the height.
+    pub glacier_freezing_temperature: f32,
+    /// determines how much colder it gets with height.
+    pub glacier_lapse_rate: f32,
+    /// sets how much ice accumulates or melts on a pixel during a single iteration.
+    pub glacier_accumulation: f32,
+    /// determines which fraction of the ice flows to lower neighbors during a single iteration.
+    /// ice is a very viscous fluid, thus this should be small.
+    pub glacier_flow_rate: f32,
+    /// has a direct effect on how much material is removed by the flowing ice.
+    pub glacier_erode_speed: f32,
+    /// the normalized height below which terrain is considered to be ocean. used by features that
+    /// distinguish between oceanic and continental terrain.
+    pub sea_level: f32,
+    /// determines how many volcanic islands are placed along convergent boundaries of two oceanic
+    /// continents, i.e. the probability that a cone is placed on a pixel of the arc. these chains
+    /// of islands are called island arcs. 0 disables island arcs.
+    pub island_arc_density: f32,
+    /// determines how far away from the continental boundary the island arc is placed, in pixels.
+    /// the arc is placed on the continent that is pushed on top of the other one.
+    pub island_arc_offset: usize,
+    /// after erosion, every pixel is replaced with the median of the surrounding pixels in this
+    /// radius. this removes single pixel spikes, which erosion sometimes leaves behind, without
+    /// blurring the terrain. 0 disables the filter.
+    pub median_filter_radius: usize,
+    /// the standard deviation of the blur used by the unsharp mask. larger values emphasize
+    /// larger features, like whole ridges, smaller values emphasize fine detail.
+    pub unsharp_mask_sigma: f32,
+    /// after erosion, the terrain is sharpened by adding `amount` times the difference between
+    /// the terrain and its blurred version. this re-emphasizes ridges that were smoothed out. 0
+    /// disables the filter.
+    pub unsharp_mask_amount: f32,
+    /// if true, `run_with_layers` also returns the contributions of the individual stages. this
+    /// requires additional memory for 3 more copies of the height maps.
+    pub keep_layers: bool,
+    /// if true, `run` returns `TerrainError::DegenerateSeed` right after the continents are
+    /// placed, if they all start on the same side or one of them is much larger than the others.
+    /// this is cheap compared to the rest of the generation, and allows to skip bad seeds fast.
+    pub reject_degenerate: bool,
+    /// if true, the sum of the noise layers and the height changes of erosion are accumulated in
+    /// `f64`, and only converted to `f32` when they are stored. this doesn't guarantee that the
+    /// same seed produces bit-identical terrain on all platforms, but it reduces the rounding
+    /// errors that add up differently between them. costs additional memory for one `f64` per
+    /// pixel and is slightly slower.
+    pub high_precision: bool,
 }
 
 impl Default for Args {
@@ -160,8 +316,15 @@ impl Default for Args {
             seed,
             width,
             continent_count: 6,
+            continent_growth_bias: 0.0,
+            continent_min_speed: 1.0,
+            continent_max_speed: 1.0,
             continental_mountain_thickness: width / 2,
+            boundary_style: BoundaryStyle::Sharp,
+            boundary_falloff: BoundaryFalloff::Quadratic,
+            noise_kind: NoiseKind::Perlin,
             fractal_main_layer: 2,
+            spectral_beta: 2.0,
             fractal_weight: 0.25,
             erosion_kind: ErosionKind::Rng,
             erosion_iterations: width * width * 6,
@@ -176,22 +339,109 @@ impl Default for Args {
             erosion_deposit_speed: 0.003,
             erosion_gravity: 4.0,
             erosion_evaporate_speed: 0.01,
+            glacier_iterations: 0,
+            glacier_freezing_temperature: 0.25,
+            glacier_lapse_rate: 0.5,
+            glacier_accumulation: 0.001,
+            glacier_flow_rate: 0.1,
+            glacier_erode_speed: 0.05,
+            sea_level: 0.25,
+            island_arc_density: 0.0,
+            island_arc_offset: width / 16,
+            median_filter_radius: 0,
+            unsharp_mask_sigma: 2.0,
+            unsharp_mask_amount: 0.0,
+            keep_layers: false,
+            reject_degenerate: false,
+            high_precision: false,
         }
     }
 }
 
+/// converts the normalized heights of the height maps into distances from the center of the
+/// planet. a height of `sea_level` lies on the surface of a sphere with radius `planet_radius`,
+/// and a height of 1 lies `max_elevation` above it. heights below `sea_level` are scaled the same
+/// way, thus the ocean floor lies below the sphere. `sea_level` must be smaller than 1. all
+/// exporters take a `VerticalScale`, such that their outputs agree with each other.
+#[derive(Debug, Clone, Copy)]
+pub struct VerticalScale {
+    pub sea_level: f32,
+    pub planet_radius: f32,
+    pub max_elevation: f32,
+}
+
+impl VerticalScale {
+    /// returns the distance from the center of the planet for the normalized height `h`
+    pub fn radius(self, h: f32) -> f32 {
+        let elevation = (h - self.sea_level) / (1.0 - self.sea_level) * self.max_elevation;
+        self.planet_radius + elevation
+    }
+
+    /// returns the position of the normalized height `h`, in the direction of `p`
+    pub fn displace(self, p: Vec3, h: f32) -> Vec3 {
+        p.normalize() * self.radius(h)
+    }
+}
+
+/// the contributions of the individual stages of `run`. `continents + noise` is the terrain before
+/// it is normalized and weighted. `erosion` is relative to the weighted terrain. glaciers, island
+/// arcs and post processing are not included.
+#[allow(dead_code)]
+// justification: public api. client code may inspect or recombine the layers
+#[derive(Debug, Clone)]
+pub struct Layers {
+    /// the height of the continental boundaries
+    pub continents: Vec<HeightMap>,
+    /// the sum of all noise layers, which was added to `continents`
+    pub noise: Vec<HeightMap>,
+    /// the height that erosion added or removed
+    pub erosion: Vec<HeightMap>,
+}
+
+#[derive(Debug, Clone)]
 pub struct HeightMap {
     pub values: Vec<f32>,
     pub side: Side,
 }
 
-pub fn run(args: Args) -> Vec<HeightMap> {
+impl HeightMap {
+    /// returns the position of each pixel on the sphere, displaced along its normal by the height.
+    /// the points are in the same order as `values`.
+    #[allow(dead_code)]
+    // justification: public api. client code may use it to export point clouds
+    pub fn to_sphere_points(&self, width: usize, vertical_scale: VerticalScale) -> Vec<Vec3> {
+        self.values
+            .iter()
+            .enumerate()
+            .map(|(i, h)| {
+                let p = position_on_sphere((i % width, i / width), width, self.side);
+                vertical_scale.displace(p, *h)
+            })
+            .collect()
+    }
+}
+
+pub fn run(args: Args) -> Result<Vec<HeightMap>, TerrainError> {
+    let (result, _) = run_with_layers(args)?;
+    Ok(result)
+}
+
+/// same as `run`, but if `keep_layers` is set, also returns the contributions of the individual
+/// stages
+pub fn run_with_layers(args: Args) -> Result<(Vec<HeightMap>, Option<Layers>), TerrainError> {
     let Args {
         seed,
         width,
         continent_count,
+        continent_growth_bias,
+        continent_min_speed,
+        continent_max_speed,
         continental_mountain_thickness,
+        boundary_style,
+        boundary_falloff,
+        noise_kind,
         fractal_main_layer,
+        spectral_beta,
         fractal_weight,
         erosion_kind,
         erosion_iterations,
@@ -206,8 +456,27 @@ pub fn run(args: Args) -> Vec<HeightMap> {
         erosion_deposit_speed,
         erosion_gravity,
         erosion_evaporate_speed,
+        glacier_iterations,
+        glacier_freezing_temperature,
+        glacier_lapse_rate,
+        glacier_accumulation,
+        glacier_flow_rate,
+        glacier_erode_speed,
+        sea_level,
+        island_arc_density,
+        island_arc_offset,
+        median_filter_radius,
+        unsharp_mask_sigma,
+        unsharp_mask_amount,
+        keep_layers,
+        reject_degenerate,
+        high_precision,
     } = args;
 
+    if continent_min_speed > continent_max_speed {
+        return Err(TerrainError::InvalidSpeedRange);
+    }
+
     eprintln!("seed: {:?}", seed);
     let mut rng = Rng::new(seed);
 
@@ -254,6 +523,9 @@ pub fn run(args: Args) -> Vec<HeightMap> {
             },
             height_map: RefCell::new(ProtoHeightMap::new(Side::F, width)),
         },
+        // the frames below are written out instead of using `Mat2::rotation`, because the gradients
+        // on both sides of an edge must match exactly, and `Mat2::rotation` is not exact for
+        // quarter turns
         ProtoSide {
             perlin_sampler: PerlinSampler {
                 offset: (1, -1),
@@ -320,259 +592,32 @@ pub fn run(args: Args) -> Vec<HeightMap> {
         }
     }
 
+    let mut growth_rngs = Vec::with_capacity(continent_count);
     for (i, starting_position) in starting_positions.into_iter().enumerate() {
         let continent = &mut continents[i];
         continent.origin = starting_position.clone();
         continent.discovered_pixels.push(starting_position);
         continent.rotation_axis = rng.next_dir_3();
+        // only draw if there is something to draw, such that the default of equal speeds does not
+        // advance the rng
+        continent.speed = if continent_min_speed == continent_max_speed {
+            continent_min_speed
+        } else {
+            rng.next_f32_between(continent_min_speed, continent_max_speed)
+        };
+        growth_rngs.push(rng.fork());
     }
 
-    let mut discovered_pixel_count = 0;
-    loop {
-        // discover new pixels
-        let mut new_pixel_was_discovered = false;
-
-        for (continent_index, continent) in continents.iter_mut().enumerate() {
-            let mut pixel = None;
-            loop {
-                if continent.discovered_pixels.is_empty() {
-                    break;
-                }
-
-                let min = 0i32;
-                let max = continent.discovered_pixels.len() as i32 - 1;
-                let index = rng.next_i32_between(min, max) as usize;
-                let candidate = continent.discovered_pixels.swap_remove(index);
-
-                let side = &mut sides[candidate.side.to_index()];
-                let mut h = side.height_map.borrow().get(candidate.ix, candidate.iy);
-
-                if h.continent_index != usize::MAX {
-                    continue;
-                }
-
-                new_pixel_was_discovered = true;
-
-                h.continent_index = continent_index;
-                if discovered_pixel_count % 1000000 == 0 {
-                    let total = width * width * 6;
-                    let progress = (discovered_pixel_count as f32 / total as f32) * 100.0;
-                    eprintln!("[1/8] generate continents... {}%", progress);
-                }
-                discovered_pixel_count += 1;
-
-                side.height_map
-                    .borrow_mut()
-                    .set(candidate.ix, candidate.iy, h);
-
-                pixel = Some(candidate);
-                break;
-            }
-
-            let Some(pixel) = pixel else {
-                continue;
-            };
-
-            // walk left
-            let new_pixel = if pixel.ix == 0 {
-                match pixel.side {
-                    // left -> front
-                    Side::L => ContinentPixel {
-                        side: Side::F,
-                        ix: width - 1,
-                        iy: pixel.iy,
-                    },
-                    // back -> left
-                    Side::B => ContinentPixel {
-                        side: Side::L,
-                        ix: width - 1,
-                        iy: pixel.iy,
-                    },
-                    // right -> back
-                    Side::R => ContinentPixel {
-                        side: Side::B,
-                        ix: width - 1,
-                        iy: pixel.iy,
-                    },
-                    // front -> right
-                    Side::F => ContinentPixel {
-                        side: Side::R,
-                        ix: width - 1,
-                        iy: pixel.iy,
-                    },
-                    // up -> left
-                    Side::U => ContinentPixel {
-                        side: Side::L,
-                        ix: pixel.iy,
-                        iy: 0,
-                    },
-                    // down -> left
-                    Side::D => ContinentPixel {
-                        side: Side::L,
-                        ix: width - 1 - pixel.iy,
-                        iy: width - 1,
-                    },
-                }
-            } else {
-                ContinentPixel {
-                    side: pixel.side,
-                    ix: pixel.ix - 1,
-                    iy: pixel.iy,
-                }
-            };
-            continent.discovered_pixels.push(new_pixel);
-
-            // walk right
-            let new_pixel = if pixel.ix == width - 1 {
-                match pixel.side {
-                    // left -> back
-                    Side::L => ContinentPixel {
-                        side: Side::B,
-                        ix: 0,
-                        iy: pixel.iy,
-                    },
-                    // back -> right
-                    Side::B => ContinentPixel {
-                        side: Side::R,
-                        ix: 0,
-                        iy: pixel.iy,
-                    },
-                    // right -> front
-                    Side::R => ContinentPixel {
-                        side: Side::F,
-                        ix: 0,
-                        iy: pixel.iy,
-                    },
-                    // front -> left
-                    Side::F => ContinentPixel {
-                        side: Side::L,
-                        ix: 0,
-                        iy: pixel.iy,
-                    },
-                    // up -> right
-                    Side::U => ContinentPixel {
-                        side: Side::R,
-                        ix: width - 1 - pixel.iy,
-                        iy: 0,
-                    },
-                    // down -> right
-                    Side::D => ContinentPixel {
-                        side: Side::R,
-                        ix: pixel.iy,
-                        iy: width - 1,
-                    },
-                }
-            } else {
-                ContinentPixel {
-                    side: pixel.side,
-                    ix: pixel.ix + 1,
-                    iy: pixel.iy,
-                }
-            };
-            continent.discovered_pixels.push(new_pixel);
-
-            // walk up
-            let new_pixel = if pixel.iy == 0 {
-                match pixel.side {
-                    // left -> up
-                    Side::L => ContinentPixel {
-                        side: Side::U,
-                        ix: 0,
-                        iy: pixel.ix,
-                    },
-                    // back -> up
-                    Side::B => ContinentPixel {
-                        side: Side::U,
-                        ix: pixel.ix,
-                        iy: width - 1,
-                    },
-                    // right -> up
-                    Side::R => ContinentPixel {
-                        side: Side::U,
-                        ix: width - 1,
-                        iy: width - 1 - pixel.ix,
-                    },
-                    // front -> up
-                    Side::F => ContinentPixel {
-                        side: Side::U,
-                        ix: width - 1 - pixel.ix,
-                        iy: 0,
-                    },
-                    // up -> front
-                    Side::U => ContinentPixel {
-                        side: Side::F,
-                        ix: width - 1 - pixel.ix,
-                        iy: 0,
-                    },
-                    // down -> back
-                    Side::D => ContinentPixel {
-                        side: Side::B,
-                        ix: pixel.ix,
-                        iy: width - 1,
-                    },
-                }
-            } else {
-                ContinentPixel {
-                    side: pixel.side,
-                    ix: pixel.ix,
-                    iy: pixel.iy - 1,
-                }
-            };
-            continent.discovered_pixels.push(new_pixel);
-
-            // walk down
-            let new_pixel = if pixel.iy == width - 1 {
-                match pixel.side {
-                    // left -> down
-                    Side::L => ContinentPixel {
-                        side: Side::D,
-                        ix: 0,
-                        iy: width - 1 - pixel.ix,
-                    },
-                    // back -> down
-                    Side::B => ContinentPixel {
-                        side: Side::D,
-                        ix: pixel.ix,
-                        iy: 0,
-                    },
-                    // right -> down
-                    Side::R => ContinentPixel {
-                        side: Side::D,
-                        ix: width - 1,
-                        iy: pixel.ix,
-                    },
-                    // front -> down
-                    Side::F => ContinentPixel {
-                        side: Side::D,
-                        ix: width - 1 - pixel.ix,
-                        iy: width - 1,
-                    },
-                    // up -> back
-                    Side::U => ContinentPixel {
-                        side: Side::B,
-                        ix: pixel.ix,
-                        iy: 0,
-                    },
-                    // down -> front
-                    Side::D => ContinentPixel {
-                        side: Side::F,
-                        ix: width - 1 - pixel.ix,
-                        iy: 0,
-                    },
-                }
-            } else {
-                ContinentPixel {
-                    side: pixel.side,
-                    ix: pixel.ix,
-                    iy: pixel.iy + 1,
-                }
-            };
-            continent.discovered_pixels.push(new_pixel);
-        }
+    grow_continents(
+        &mut continents,
+        &mut growth_rngs,
+        &sides,
+        width,
+        continent_growth_bias,
+    );
 
-        if !new_pixel_was_discovered {
-            break;
-        }
+    if reject_degenerate && is_degenerate(&continents, &sides) {
+        return Err(TerrainError::DegenerateSeed);
     }
 
     // find edges
@@ -723,47 +768,14 @@ pub fn run(args: Args) -> Vec<HeightMap> {
                 let continent = &continents[h.continent_index];
                 let continent_ = &continents[h_.continent_index];
 
-                // calculate boundary height
-                let angle = 2.0 * PI / (4 * width) as f32;
-                let q = Quat::angle_axis(angle, continent.rotation_axis);
-                let q_ = Quat::angle_axis(angle, continent_.rotation_axis);
-
                 let p = position_on_sphere((ix, iy), width, height_map.borrow().side);
                 let p_ = position_on_sphere((ix_, iy_), width, height_map_.borrow().side);
+                let boundary_height =
+                    boundary_height(p, p_, continent, continent_, width, boundary_style);
 
-                let v = (q.rotate(p) - p).normalize();
-                let v_ = (q_.rotate(p_) - p_).normalize();
-
-                let origin_pixel = continent.origin.clone();
-
-                let o = position_on_sphere(
-                    (origin_pixel.ix, origin_pixel.iy),
-                    width,
-                    origin_pixel.side,
-                );
-                let d = p - o;
-                let d_ = p_ - o;
-
-                // formular for smoother, but in my opinion
-                // less interesting terrain:
-                // let m = (p * p_) / 2.0;
-                // let d = p - m;
-                // let d_ = m - p_;
-
-                let dot = Vec3::dot(v.normalize(), d.normalize());
-                let dot_ = Vec3::dot(v_.normalize(), d_.normalize());
-
-                let boundary_height = match (dot.is_sign_positive(), dot_.is_sign_positive()) {
-                    (false, false) => -(dot * dot_),
-                    (true, false) => -(dot * dot_),
-                    (false, true) => -(dot * dot_),
-                    (true, true) => dot * dot_,
-                };
-
-                // https://www.desmos.com/calculator/4p8se0qln8
                 let m = continental_mountain_thickness as f32;
-                let x = -m + generation as f32;
-                let weight = (x * x) / (m * m);
+                let x = generation as f32 / m;
+                let weight = boundary_falloff.weight(x);
 
                 h.height = boundary_height * weight;
                 height_map.borrow_mut().set(ix, iy, h);
@@ -773,140 +785,30 @@ pub fn run(args: Args) -> Vec<HeightMap> {
 
     normalize(&mut sides, Some(129.8125 / 255.0));
 
-    // sides
-    let mut count = 0;
-    let layers = f32::log2(width as f32) as usize - 1;
-    let total = sides.len() * width * layers;
-
-    for side in sides.iter() {
-        let ProtoSide {
-            perlin_sampler,
-            height_map,
-        } = side;
-
-        let mut layer = 0;
-        loop {
-            let grid_width: i32 = 1 << (layer + 1);
-
-            // https://www.desmos.com/calculator/xnwqm8vdez
-            let a = 1.0;
-            let b = fractal_main_layer as f32;
-            let x = layer as f32;
-            let grid_weight = fractal_weight / (f32::abs(a * x - a * b) + 1.0);
-
-            layer += 1;
-
-            if grid_width >= width as i32 {
-                break;
-            }
-
-            for iy in 0..width {
-                if iy % 1000 == 0 {
-                    let process = (count as f32 / total as f32) * 100.0;
-                    eprintln!("[5/8] generating noise... {}%", process,);
-                }
-                count += 1;
-
-                for ix in 0..width {
-                    let coord = Vec2(ix as f32 + 0.5, iy as f32 + 0.5);
-                    let heigh_map_width = height_map.borrow().width as f32;
-                    let size = Vec2(heigh_map_width, heigh_map_width);
-                    let normalized = coord / size;
-                    let grid = Vec2(grid_width as f32, grid_width as f32);
-                    let p = normalized * grid;
-
-                    // this closure connects the edges and corners of different sizes, to
-                    // ensure that the perlin noise ist continuous over the whole cube
-                    let apply_net = |ix: i32, iy: i32| {
-                        let offset_x = perlin_sampler.offset.0 * grid_width;
-                        let offset_y = perlin_sampler.offset.1 * grid_width;
-                        let default_x = ix + offset_x;
-                        let default_y = iy + offset_y;
-                        let default = ((default_x, default_y), Mat2::identity());
-
-                        #[allow(clippy::if_same_then_else)]
-                        // justification: makes things easier to reason about. each branch is an
-                        // individual corner, edge or center pixel
-                        if ix == 0 {
-                            if iy == 0 {
-                                ((default_x, default_y), Mat2::init(0.0))
-                            } else if iy == grid_width {
-                                ((default_x, default_y), Mat2::init(0.0))
-                            } else {
-                                perlin_sampler
-                                    .edge0
-                                    .as_ref()
-                                    .map(|edge| edge(iy, (grid_width, grid_width)))
-                                    .unwrap_or(default)
-                            }
-                        } else if ix == grid_width {
-                            if iy == 0 {
-                                ((default_x, default_y), Mat2::init(0.0))
-                            } else if iy == grid_width {
-                                ((default_x, default_y), Mat2::init(0.0))
-                            } else {
-                                perlin_sampler
-                                    .edge1
-                                    .as_ref()
-                                    .map(|edge| edge(iy, (grid_width, grid_width)))
-                                    .unwrap_or(default)
-                            }
-                        } else if iy == 0 {
-                            perlin_sampler
-                                .edge2
-                                .as_ref()
-                                .map(|edge| edge(ix, (grid_width, grid_width)))
-                                .unwrap_or(default)
-                        } else if iy == grid_width {
-                            perlin_sampler
-                                .edge3
-                                .as_ref()
-                                .map(|edge| edge(ix, (grid_width, grid_width)))
-                                .unwrap_or(default)
-                        } else {
-                            default
-                        }
-                    };
-
-                    // perlin noise
-                    let m0 = p.x().floor() as i32;
-                    let m1 = m0 + 1;
-                    let n0 = p.y().floor() as i32;
-                    let n1 = n0 + 1;
-
-                    let (iq0, mat0) = apply_net(m0, n0);
-                    let (iq1, mat1) = apply_net(m1, n0);
-                    let (iq2, mat2) = apply_net(m0, n1);
-                    let (iq3, mat3) = apply_net(m1, n1);
-                    let g0 = mat0 * random_gradient(iq0.0, iq0.1, seed);
-                    let g1 = mat1 * random_gradient(iq1.0, iq1.1, seed);
-                    let g2 = mat2 * random_gradient(iq2.0, iq2.1, seed);
-                    let g3 = mat3 * random_gradient(iq3.0, iq3.1, seed);
-
-                    let q0 = Vec2(m0 as f32, n0 as f32);
-                    let q1 = Vec2(m1 as f32, n0 as f32);
-                    let q2 = Vec2(m0 as f32, n1 as f32);
-                    let q3 = Vec2(m1 as f32, n1 as f32);
-
-                    let s0 = g0.dot(p - q0);
-                    let s1 = g1.dot(p - q1);
-                    let s2 = g2.dot(p - q2);
-                    let s3 = g3.dot(p - q3);
+    let continents_layer = keep_layers.then(|| prepare_proto_sides(&sides));
 
-                    let h = |x: f32| (3.0 - x * 2.0) * x * x;
-                    let Vec2(x, y) = p - q0;
-                    let f0 = s0 * h(1.0 - x) + s1 * h(x);
-                    let f1 = s2 * h(1.0 - x) + s3 * h(x);
-                    let f = f0 * h(1.0 - y) + f1 * h(y);
-                    // perlin noise end
-
-                    let mut h = height_map.borrow().get(ix, iy);
-                    h.height += f * grid_weight;
-                    height_map.borrow_mut().set(ix, iy, h);
-                }
-            }
+    // sides
+    match noise_kind {
+        NoiseKind::Perlin => {
+            add_perlin_noise(
+                &sides,
+                width,
+                fractal_main_layer,
+                fractal_weight,
+                seed,
+                high_precision,
+            );
         }
-    } // end sides
+        NoiseKind::Spectral => {
+            add_spectral_noise(&sides, width, spectral_beta, fractal_weight, &mut rng);
+        }
+    }
+
+    let noise_layer = continents_layer.as_ref().map(|continents_layer| {
+        let mut noise_layer = prepare_proto_sides(&sides);
+        subtract_height_maps(&mut noise_layer, continents_layer);
+        noise_layer
+    });
 
     // normalize and apply weight to heightmap
     eprintln!("[6/8] apply weight...");
@@ -930,6 +832,8 @@ pub fn run(args: Args) -> Vec<HeightMap> {
 
     normalize(&mut sides, None);
 
+    let uneroded = keep_layers.then(|| prepare_proto_sides(&sides));
+
     // erosion
     eprintln!("[7/8] find erosion stride...");
 
@@ -961,6 +865,7 @@ pub fn run(args: Args) -> Vec<HeightMap> {
 
     eprintln!("[7/8] stride {}, ideal: {}", stride, ideal_stride);
 
+    let mut accumulator = HeightAccumulator::new(&sides, width, high_precision);
     let mut idrop = rng.next_usize();
     for i in 0..erosion_iterations {
         if i % 100_000 == 0 {
@@ -987,8 +892,7 @@ pub fn run(args: Args) -> Vec<HeightMap> {
         for _lifetime in 0..erosion_max_lifetime {
             let (gradient, height) = calculate_gradient_and_height(pos, width, side, &sides, eko);
 
-            dir.set_x(dir.x() * erosion_inertia - gradient.x() * (1.0 - erosion_inertia));
-            dir.set_y(dir.y() * erosion_inertia - gradient.y() * (1.0 - erosion_inertia));
+            dir = Vec2::lerp_unclamped(-gradient, dir, erosion_inertia);
             let dir_ = dir.normalize();
             if dir.is_nan().any() {
                 break;
@@ -998,166 +902,16 @@ pub fn run(args: Args) -> Vec<HeightMap> {
             dir = dir_;
 
             // droplet may crossed to another side. we need to remap
-            loop {
-                let w = width as f32;
+            let Some((new_pos, new_side, frame)) = wrap_position(pos, width, side) else {
+                break;
+            };
+            pos = new_pos;
+            side = new_side;
+            dir = frame * dir;
+            eko.rotate(frame);
 
-                let Vec2(x, y) = pos;
-
-                if x < 0.0 {
-                    // droplet moved left
-                    match side {
-                        Side::L => {
-                            side = Side::F;
-                            pos = Vec2(w + x, y);
-                        }
-                        Side::B => {
-                            side = Side::L;
-                            pos = Vec2(w + x, y);
-                        }
-                        Side::R => {
-                            side = Side::B;
-                            pos = Vec2(w + x, y);
-                        }
-                        Side::F => {
-                            side = Side::R;
-                            pos = Vec2(w + x, y);
-                        }
-                        Side::U => {
-                            side = Side::L;
-                            pos = Vec2(y, -x);
-                            // rotate dir ccw
-                            dir = Vec2(-dir.y(), dir.x());
-                            eko.rotate_ccw();
-                        }
-                        Side::D => {
-                            side = Side::L;
-                            pos = Vec2(w - y, w + x);
-                            // rotate dir cw
-                            dir = Vec2(dir.y(), -dir.x());
-                            eko.rotate_cw();
-                        }
-                    }
-                } else if x > w {
-                    // droplet moved right
-                    match side {
-                        Side::L => {
-                            side = Side::B;
-                            pos = Vec2(x - w, y);
-                        }
-                        Side::B => {
-                            side = Side::R;
-                            pos = Vec2(x - w, y);
-                        }
-                        Side::R => {
-                            side = Side::F;
-                            pos = Vec2(x - w, y);
-                        }
-                        Side::F => {
-                            side = Side::L;
-                            pos = Vec2(x - w, y);
-                        }
-                        Side::U => {
-                            side = Side::R;
-                            pos = Vec2(w - y, x - w);
-                            // rotate dir cw
-                            dir = Vec2(dir.y(), -dir.x());
-                            eko.rotate_cw();
-                        }
-                        Side::D => {
-                            side = Side::R;
-                            pos = Vec2(y, 2.0 * w - x);
-                            // rotate dir ccw
-                            dir = Vec2(-dir.y(), dir.x());
-                            eko.rotate_ccw();
-                        }
-                    }
-                } else if y < 0.0 {
-                    // droplet moved up
-                    match side {
-                        Side::L => {
-                            side = Side::U;
-                            pos = Vec2(-y, x);
-                            // rotate dir cw
-                            dir = Vec2(dir.y(), -dir.x());
-                            eko.rotate_cw();
-                        }
-                        Side::B => {
-                            side = Side::U;
-                            pos = Vec2(x, w + y);
-                        }
-                        Side::R => {
-                            side = Side::U;
-                            pos = Vec2(w + y, w - x);
-                            // rotate dir ccw
-                            dir = Vec2(-dir.y(), dir.x());
-                            eko.rotate_ccw();
-                        }
-                        Side::F => {
-                            side = Side::U;
-                            pos = Vec2(w - x, -y);
-                            // rotate dir 180°
-                            dir = Vec2(dir.y(), dir.x());
-                            eko.rotate_180();
-                        }
-                        Side::U => {
-                            side = Side::F;
-                            pos = Vec2(w - x, -y);
-                            // rotate dir 180°
-                            dir = Vec2(dir.y(), dir.x());
-                            eko.rotate_180();
-                        }
-                        Side::D => {
-                            side = Side::B;
-                            pos = Vec2(x, w + y);
-                        }
-                    }
-                } else if y > w {
-                    // droplet moved down
-                    match side {
-                        Side::L => {
-                            side = Side::D;
-                            pos = Vec2(y - w, w - x);
-                            // rotate dir ccw
-                            dir = Vec2(-dir.y(), dir.x());
-                            eko.rotate_ccw();
-                        }
-                        Side::B => {
-                            side = Side::D;
-                            pos = Vec2(x, y - w);
-                        }
-                        Side::R => {
-                            side = Side::D;
-                            pos = Vec2(2.0 * w - y, x);
-                            // rotate dir cw
-                            dir = Vec2(dir.y(), -dir.x());
-                            eko.rotate_cw();
-                        }
-                        Side::F => {
-                            side = Side::D;
-                            pos = Vec2(w - x, 2.0 * w - y);
-                            // rotate dir 180°
-                            dir = Vec2(dir.y(), dir.x());
-                            eko.rotate_180();
-                        }
-                        Side::U => {
-                            side = Side::B;
-                            pos = Vec2(x, y - w);
-                        }
-                        Side::D => {
-                            side = Side::F;
-                            pos = Vec2(w - x, 2.0 * w - y);
-                            // rotate dir 180°
-                            dir = Vec2(dir.y(), dir.x());
-                            eko.rotate_180();
-                        }
-                    }
-                } else {
-                    break;
-                }
-            }
-
-            let (_, new_height) = calculate_gradient_and_height(pos, width, side, &sides, eko);
-            let delta_height = new_height - height;
+            let (_, new_height) = calculate_gradient_and_height(pos, width, side, &sides, eko);
+            let delta_height = new_height - height;
 
             let sediment_capacity = f32::max(
                 -delta_height * speed * water * erosion_sediment_capacity_factor,
@@ -1203,10 +957,10 @@ pub fn run(args: Args) -> Vec<HeightMap> {
                 let isw = (i.0 + osw.0, i.1 + osw.1);
                 let ise = (i.0 + ose.0, i.1 + ose.1);
 
-                deposit_sediment(inw, width, side, &sides, deposit_nw);
-                deposit_sediment(ine, width, side, &sides, deposit_ne);
-                deposit_sediment(isw, width, side, &sides, deposit_sw);
-                deposit_sediment(ise, width, side, &sides, deposit_se);
+                deposit_sediment(inw, width, side, &sides, &mut accumulator, deposit_nw);
+                deposit_sediment(ine, width, side, &sides, &mut accumulator, deposit_ne);
+                deposit_sediment(isw, width, side, &sides, &mut accumulator, deposit_sw);
+                deposit_sediment(ise, width, side, &sides, &mut accumulator, deposit_se);
             } else {
                 let amount_to_erode = f32::min(
                     (sediment_capacity - sediment) * erosion_erode_speed,
@@ -1218,21 +972,15 @@ pub fn run(args: Args) -> Vec<HeightMap> {
                 let node_x = usize::min(node_x, width - 1);
                 let node_y = usize::min(node_y, width - 1);
 
-                let mut h = sides[side.to_index()]
-                    .height_map
-                    .borrow()
-                    .get(node_x, node_y);
+                let height_map = &sides[side.to_index()].height_map;
+                let h = height_map.borrow().get(node_x, node_y);
                 let delta_sediment = if h.height < amount_to_erode {
                     h.height
                 } else {
                     amount_to_erode
                 };
-                h.height -= delta_sediment;
+                accumulator.add(height_map, node_x, node_y, -delta_sediment);
                 sediment += delta_sediment;
-                sides[side.to_index()]
-                    .height_map
-                    .borrow_mut()
-                    .set(node_x, node_y, h);
             }
 
             speed = f32::sqrt(f32::max(
@@ -1244,18 +992,145 @@ pub fn run(args: Args) -> Vec<HeightMap> {
 
         if i % erosion_normalize_mod == 0 {
             normalize(&mut sides, None);
+            accumulator.reload(&sides);
         }
     } // erosion iterations
 
+    let layers = match (continents_layer, noise_layer, uneroded) {
+        (Some(continents), Some(noise), Some(uneroded)) => {
+            let mut erosion = prepare_proto_sides(&sides);
+            subtract_height_maps(&mut erosion, &uneroded);
+            Some(Layers {
+                continents,
+                noise,
+                erosion,
+            })
+        }
+        _ => None,
+    };
+
+    // glaciers
+    if glacier_iterations > 0 {
+        let glacier_args = GlacierArgs {
+            iterations: glacier_iterations,
+            freezing_temperature: glacier_freezing_temperature,
+            lapse_rate: glacier_lapse_rate,
+            accumulation: glacier_accumulation,
+            flow_rate: glacier_flow_rate,
+            erode_speed: glacier_erode_speed,
+        };
+        simulate_glaciers(&sides, width, glacier_args);
+        normalize(&mut sides, None);
+    }
+
+    // island arcs
+    if island_arc_density > 0.0 {
+        eprintln!("[7/8] place island arcs...");
+        normalize(&mut sides, None);
+
+        let cone_radius = usize::max(island_arc_offset / 2, 2) as isize;
+        let apex = sea_level + 0.1;
+
+        for (side_index, side_values) in continent_boundary_space.iter().enumerate() {
+            let side = Side::from(side_index);
+
+            for iy in 0..width {
+                for ix in 0..width {
+                    let (_, _, generation) =
+                        side_values[iy * width + ix].expect("all pixels to be discovered");
+
+                    if generation != island_arc_offset {
+                        continue;
+                    }
+
+                    let h = sides[side_index].height_map.borrow().get(ix, iy);
+                    if h.height >= sea_level {
+                        continue;
+                    }
+
+                    let (b, bside) =
+                        walk_to_boundary((ix, iy), side, width, &continent_boundary_space);
+
+                    // island arcs only form where oceanic crust meets oceanic crust. where the
+                    // neighbor is continental, the overriding continent forms a mountain range
+                    // instead
+                    let Some((nb, nbside)) = neighbor_across_boundary(b, bside, width, &sides)
+                    else {
+                        continue;
+                    };
+                    let nh = sides[nbside.to_index()].height_map.borrow().get(nb.0, nb.1);
+                    if nh.height >= sea_level {
+                        continue;
+                    }
+
+                    if !is_overriding_convergent_boundary(
+                        (ix, iy),
+                        side,
+                        b,
+                        bside,
+                        nb,
+                        nbside,
+                        width,
+                        &sides,
+                        &continents,
+                    ) {
+                        continue;
+                    }
+
+                    if rng.next_f32() >= island_arc_density {
+                        continue;
+                    }
+
+                    // drop a cone
+                    for oy in -cone_radius..=cone_radius {
+                        for ox in -cone_radius..=cone_radius {
+                            let distance = f32::sqrt((ox * ox + oy * oy) as f32);
+                            let x = distance / cone_radius as f32;
+                            if x > 1.0 {
+                                continue;
+                            }
+
+                            let i = (ix as isize + ox, iy as isize + oy);
+                            let Ok(RemappedIndex {
+                                ix: ix_,
+                                iy: iy_,
+                                side: side_,
+                            }) = RemappedIndex::new(i, width, side)
+                            else {
+                                continue;
+                            };
+
+                            let height_map = &sides[side_.to_index()].height_map;
+                            let mut h = height_map.borrow().get(ix_, iy_);
+                            let cone = crate::common::mix(apex, h.height, x);
+                            h.height = f32::max(h.height, cone);
+                            height_map.borrow_mut().set(ix_, iy_, h);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
     // prepare result
     eprintln!("[8/8] prepare result...");
 
     normalize(&mut sides, None);
-    let result = prepare_proto_sides(sides);
+    let mut result = prepare_proto_sides(sides);
+
+    if median_filter_radius > 0 {
+        eprintln!("[8/8] apply median filter...");
+        median_filter(&mut result, width, median_filter_radius);
+    }
+
+    if unsharp_mask_amount != 0.0 {
+        eprintln!("[8/8] apply unsharp mask...");
+        unsharp_mask(&mut result, width, unsharp_mask_sigma, unsharp_mask_amount);
+    }
 
     eprintln!("done with terrain generation!");
 
-    result
+    Ok((result, layers))
 }
 
 #[derive(Clone, Copy)]
@@ -1299,6 +1174,54 @@ impl ProtoHeightMap {
     }
 }
 
+// adds height changes to the height maps. in high precision mode, a copy of the heights is kept in
+// `f64`, such that many small changes are summed without rounding to `f32` after each one. the
+// copy must be reloaded whenever the heights are modified by other means.
+struct HeightAccumulator {
+    values: Option<Vec<f64>>,
+    width: usize,
+}
+
+impl HeightAccumulator {
+    fn new(sides: &[ProtoSide], width: usize, high_precision: bool) -> Self {
+        let mut result = Self {
+            values: high_precision.then(Vec::new),
+            width,
+        };
+        result.reload(sides);
+        result
+    }
+
+    fn reload(&mut self, sides: &[ProtoSide]) {
+        let Some(values) = self.values.as_mut() else {
+            return;
+        };
+
+        values.clear();
+        for side in sides.iter() {
+            let height_map = side.height_map.borrow();
+            values.extend(height_map.values.iter().map(|x| x.height as f64));
+        }
+    }
+
+    fn add(&mut self, height_map: &RefCell<ProtoHeightMap>, x: usize, y: usize, delta: f32) {
+        let mut height_map = height_map.borrow_mut();
+        let i = height_map.index(x, y);
+        let mut h = height_map.values[i];
+
+        match self.values.as_mut() {
+            Some(values) => {
+                let j = height_map.side.to_index() * self.width * self.width + i;
+                values[j] += delta as f64;
+                h.height = values[j] as f32;
+            }
+            None => h.height += delta,
+        }
+
+        height_map.values[i] = h;
+    }
+}
+
 struct ProtoSide {
     perlin_sampler: PerlinSampler,
     height_map: RefCell<ProtoHeightMap>,
@@ -1314,6 +1237,9 @@ struct PerlinSampler {
     edge3: Option<PerlinSamplerCallback>,
 }
 
+// the coordinates of a pixel, and its distance to the closest continental boundary
+type ContinentBoundarySpacePixel = Option<((usize, usize), Side, usize)>;
+
 #[derive(Default, Debug, Clone, PartialEq, Eq, Hash)]
 struct ContinentPixel {
     side: Side,
@@ -1326,6 +1252,7 @@ struct Continent {
     origin: ContinentPixel,
     discovered_pixels: Vec<ContinentPixel>,
     rotation_axis: Vec3,
+    speed: f32,
 }
 
 // erosion samples 4 cells at different steps. when the droplet goes over a cube edge, the kernel
@@ -1345,6 +1272,8 @@ struct ErosionKernelOriginOffsets {
     se: (isize, isize),
 }
 
+#[allow(clippy::derivable_impls)]
+// justification: the default is spelled out, instead of hiding it in an attribute on a variant
 impl Default for ErosionKernelOrigin {
     fn default() -> Self {
         Self::NW
@@ -1375,6 +1304,21 @@ impl ErosionKernelOrigin {
         self.rotate_cw();
     }
 
+    // rotates the kernel like `frame` rotates the axes of a side, see `remapped_frame`
+    fn rotate(&mut self, frame: Mat2) {
+        let Vec2(x, y) = frame.0;
+        if y > 0.5 {
+            // x axis turned into y axis
+            self.rotate_ccw();
+        } else if y < -0.5 {
+            // x axis turned into -y axis
+            self.rotate_cw();
+        } else if x < -0.5 {
+            // x axis turned into -x axis
+            self.rotate_180();
+        }
+    }
+
     // returns offsets for (nw, ne, sw, se)
     fn get_offsets(self) -> ErosionKernelOriginOffsets {
         match self {
@@ -1406,7 +1350,403 @@ impl ErosionKernelOrigin {
     }
 }
 
-fn position_on_sphere(texture_coordinate: (usize, usize), width: usize, side: Side) -> Vec3 {
+// the height at `p`, which belongs to `continent`, caused by the collision with `continent_`, whose
+// closest pixel is at `p_`. each continent moves with its own speed, and only the part of its
+// velocity that points towards the other continent contributes. thus the height grows with the
+// speed of both continents, and is zero if either one moves along the boundary.
+fn boundary_height(
+    p: Vec3,
+    p_: Vec3,
+    continent: &Continent,
+    continent_: &Continent,
+    width: usize,
+    boundary_style: BoundaryStyle,
+) -> f32 {
+    let angle = 2.0 * PI / (4 * width) as f32;
+    let q = Quat::angle_axis(angle, continent.rotation_axis);
+    let q_ = Quat::angle_axis(angle, continent_.rotation_axis);
+
+    let v = (q.rotate(p) - p).normalize_or_zero() * continent.speed;
+    let v_ = (q_.rotate(p_) - p_).normalize_or_zero() * continent_.speed;
+
+    let (d, d_) = match boundary_style {
+        BoundaryStyle::Sharp => {
+            let origin_pixel = &continent.origin;
+
+            let o =
+                position_on_sphere((origin_pixel.ix, origin_pixel.iy), width, origin_pixel.side);
+            (p - o, p_ - o)
+        }
+        BoundaryStyle::Smooth => {
+            let m = (p * p_) / 2.0;
+            (p - m, m - p_)
+        }
+    };
+
+    let dot = v.scalar_projection(d);
+    let dot_ = v_.scalar_projection(d_);
+
+    match (dot.is_sign_positive(), dot_.is_sign_positive()) {
+        (false, false) => -(dot * dot_),
+        (true, false) => -(dot * dot_),
+        (false, true) => -(dot * dot_),
+        (true, true) => dot * dot_,
+    }
+}
+
+// grows the continents from their discovered pixels, until every pixel is claimed. each round,
+// every continent grows by one pixel. to make the shapes independent of the order of the
+// continents, all continents pick their next pixel from the state at the start of the round, and
+// each continent has its own rng. if multiple continents picked the same pixel, the one with the
+// smallest origin wins.
+fn grow_continents(
+    continents: &mut [Continent],
+    growth_rngs: &mut [Rng],
+    sides: &[ProtoSide],
+    width: usize,
+    bias: f32,
+) {
+    let origin_key = |continent: &Continent| {
+        let ContinentPixel { side, ix, iy } = continent.origin;
+        (side.to_index(), iy, ix)
+    };
+
+    let mut discovered_pixel_count = 0;
+    loop {
+        // pick new pixels
+        let mut picks = Vec::with_capacity(continents.len());
+        for (continent, growth_rng) in continents.iter_mut().zip(growth_rngs.iter_mut()) {
+            let mut pick = None;
+            while !continent.discovered_pixels.is_empty() {
+                let index = find_frontier_index(continent, width, bias, growth_rng);
+                let candidate = continent.discovered_pixels.swap_remove(index);
+
+                if !is_claimed(&candidate, sides) {
+                    pick = Some(candidate);
+                    break;
+                }
+            }
+
+            picks.push(pick);
+        }
+
+        if picks.iter().all(Option::is_none) {
+            break;
+        }
+
+        // claim picked pixels
+        for (continent_index, pick) in picks.iter().enumerate() {
+            let Some(candidate) = pick else {
+                continue;
+            };
+
+            let side = &sides[candidate.side.to_index()];
+            let mut h = side.height_map.borrow().get(candidate.ix, candidate.iy);
+
+            let is_won = h.continent_index == usize::MAX
+                || origin_key(&continents[continent_index])
+                    < origin_key(&continents[h.continent_index]);
+            if !is_won {
+                continue;
+            }
+
+            h.continent_index = continent_index;
+            side.height_map
+                .borrow_mut()
+                .set(candidate.ix, candidate.iy, h);
+        }
+
+        // discover neighbors of claimed pixels
+        for (continent_index, (continent, pick)) in continents.iter_mut().zip(picks).enumerate() {
+            let Some(pixel) = pick else {
+                continue;
+            };
+
+            let h = sides[pixel.side.to_index()]
+                .height_map
+                .borrow()
+                .get(pixel.ix, pixel.iy);
+            if h.continent_index != continent_index {
+                continue;
+            }
+
+            if discovered_pixel_count % 1000000 == 0 {
+                let total = width * width * 6;
+                let progress = (discovered_pixel_count as f32 / total as f32) * 100.0;
+                eprintln!("[1/8] generate continents... {}%", progress);
+            }
+            discovered_pixel_count += 1;
+
+            // walk left, right, up and down. neighbors on other sides are found with the same
+            // adjacency as everywhere else
+            for offset in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                let RemappedIndex { ix, iy, side } = RemappedIndex::new(
+                    (pixel.ix as isize + offset.0, pixel.iy as isize + offset.1),
+                    width,
+                    pixel.side,
+                )
+                .expect("offsets to not go over corners, only edges");
+
+                let new_pixel = ContinentPixel { side, ix, iy };
+                if !is_claimed(&new_pixel, sides) {
+                    continent.discovered_pixels.push(new_pixel);
+                }
+            }
+        }
+    }
+}
+
+// pixels that are already claimed by a continent don't need to be discovered again. skipping them
+// keeps the frontier proportional to the length of the continents boundaries, instead of their
+// area. a pixel may still be claimed after it was discovered, thus it must be checked again when
+// the continent grows into it.
+fn is_claimed(pixel: &ContinentPixel, sides: &[ProtoSide]) -> bool {
+    let h = sides[pixel.side.to_index()]
+        .height_map
+        .borrow()
+        .get(pixel.ix, pixel.iy);
+    h.continent_index != usize::MAX
+}
+
+// a seed is degenerate, if enough continents exist to expect them to spread over multiple sides,
+// but they all start on the same one, or if a single continent is much larger than the average.
+// both happen when the starting positions are clumped together, such that the continents in the
+// middle are enclosed early and stop growing.
+fn is_degenerate(continents: &[Continent], sides: &[ProtoSide]) -> bool {
+    const MIN_CONTINENTS_TO_SPREAD: usize = 3;
+    const MAX_AREA_FACTOR: usize = 3;
+
+    let count = continents.len();
+    if count < 2 {
+        return false;
+    }
+
+    let mut origin_sides = continents
+        .iter()
+        .map(|x| x.origin.side.to_index())
+        .collect::<Vec<_>>();
+    origin_sides.sort();
+    origin_sides.dedup();
+
+    let mut areas = vec![0; count];
+    let mut total = 0;
+    for side in sides.iter() {
+        for value in side.height_map.borrow().values.iter() {
+            areas[value.continent_index] += 1;
+            total += 1;
+        }
+    }
+    let max_area = areas.iter().copied().max().unwrap_or(0);
+
+    eprintln!(
+        "continents start on {} sides, largest covers {}%",
+        origin_sides.len(),
+        max_area as f32 / total as f32 * 100.0,
+    );
+
+    let is_clumped = count >= MIN_CONTINENTS_TO_SPREAD && origin_sides.len() == 1;
+    let is_unbalanced = max_area * count > MAX_AREA_FACTOR * total;
+    is_clumped || is_unbalanced
+}
+
+// picks the index of the next pixel a continent grows into. without bias, this is a uniform pick.
+// with bias, candidates are drawn uniformly and then accepted with a probability depending on
+// their weight (rejection sampling), so that the frontier doesn't need to be sorted or weighted
+// as a whole.
+fn find_frontier_index(continent: &Continent, width: usize, bias: f32, rng: &mut Rng) -> usize {
+    const MAX_TRIES: usize = 16;
+
+    let min = 0i32;
+    let max = continent.discovered_pixels.len() as i32 - 1;
+
+    if bias == 0.0 {
+        return rng.next_i32_between(min, max) as usize;
+    }
+
+    let bias = bias.clamp(-1.0, 1.0);
+    let origin = &continent.origin;
+    let o = position_on_sphere((origin.ix, origin.iy), width, origin.side);
+    let direction = Vec3::cross(continent.rotation_axis, o).normalize();
+
+    let mut index = 0;
+    for _ in 0..MAX_TRIES {
+        index = rng.next_i32_between(min, max) as usize;
+        let candidate = &continent.discovered_pixels[index];
+        let p = position_on_sphere((candidate.ix, candidate.iy), width, candidate.side);
+
+        let alignment = if bias > 0.0 {
+            // elongated: prefer growing along the direction of movement
+            let d = (p - o).normalize();
+            if d.is_nan().any() {
+                1.0
+            } else {
+                f32::abs(Vec3::dot(d, direction))
+            }
+        } else {
+            // compact: prefer growing close to the origin
+            (Vec3::dot(p.normalize(), o.normalize()) + 1.0) / 2.0
+        };
+
+        let weight = crate::common::mix(1.0, alignment, f32::abs(bias));
+        if rng.next_f32() < weight {
+            break;
+        }
+    }
+
+    index
+}
+
+// starting at `p`, repeatedly steps to a neighbor that is closer to a continental boundary, until
+// a pixel on the boundary is reached
+fn walk_to_boundary(
+    p: (usize, usize),
+    side: Side,
+    width: usize,
+    continent_boundary_space: &[Vec<ContinentBoundarySpacePixel>],
+) -> ((usize, usize), Side) {
+    let generation_at = |(ix, iy): (usize, usize), side: Side| {
+        let (_, _, generation) = continent_boundary_space[side.to_index()][iy * width + ix]
+            .expect("all pixels to be discovered");
+        generation
+    };
+
+    let mut p = p;
+    let mut side = side;
+    let mut generation = generation_at(p, side);
+
+    while generation > 0 {
+        let mut next = None;
+        for offset in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+            let RemappedIndex {
+                ix: ix_,
+                iy: iy_,
+                side: side_,
+            } = RemappedIndex::new(
+                (p.0 as isize + offset.0, p.1 as isize + offset.1),
+                width,
+                side,
+            )
+            .expect("offsets to not go over corners, only edges");
+
+            let generation_ = generation_at((ix_, iy_), side_);
+            if generation_ < generation {
+                next = Some(((ix_, iy_), side_, generation_));
+                break;
+            }
+        }
+
+        let Some((p_, side_, generation_)) = next else {
+            break;
+        };
+
+        p = p_;
+        side = side_;
+        generation = generation_;
+    }
+
+    (p, side)
+}
+
+// returns a pixel next to the boundary pixel `b`, which belongs to the neighboring continent.
+// returns `None` if `b` is not on a boundary.
+fn neighbor_across_boundary(
+    b: (usize, usize),
+    bside: Side,
+    width: usize,
+    sides: &[ProtoSide],
+) -> Option<((usize, usize), Side)> {
+    let continent_index = sides[bside.to_index()]
+        .height_map
+        .borrow()
+        .get(b.0, b.1)
+        .continent_index;
+
+    for offset in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+        let RemappedIndex { ix, iy, side } = RemappedIndex::new(
+            (b.0 as isize + offset.0, b.1 as isize + offset.1),
+            width,
+            bside,
+        )
+        .expect("offsets to not go over corners, only edges");
+
+        let index = sides[side.to_index()]
+            .height_map
+            .borrow()
+            .get(ix, iy)
+            .continent_index;
+        if index != continent_index {
+            return Some(((ix, iy), side));
+        }
+    }
+
+    None
+}
+
+// determines whether the boundary pixel `b` lies on a convergent boundary, where the continent of
+// `b` is pushed on top of the neighboring continent. `p` is a pixel on the same continent as `b`,
+// further away from the boundary. `nb` is the pixel of the neighboring continent next to `b`, see
+// `neighbor_across_boundary`.
+#[allow(clippy::too_many_arguments)]
+fn is_overriding_convergent_boundary(
+    p: (usize, usize),
+    side: Side,
+    b: (usize, usize),
+    bside: Side,
+    nb: (usize, usize),
+    nbside: Side,
+    width: usize,
+    sides: &[ProtoSide],
+    continents: &[Continent],
+) -> bool {
+    let continent_index = sides[bside.to_index()]
+        .height_map
+        .borrow()
+        .get(b.0, b.1)
+        .continent_index;
+
+    let p_continent_index = sides[side.to_index()]
+        .height_map
+        .borrow()
+        .get(p.0, p.1)
+        .continent_index;
+    if p_continent_index != continent_index {
+        return false;
+    }
+
+    let neighbor_index = sides[nbside.to_index()]
+        .height_map
+        .borrow()
+        .get(nb.0, nb.1)
+        .continent_index;
+    if neighbor_index == continent_index {
+        return false;
+    }
+
+    let continent = &continents[continent_index];
+    let neighbor = &continents[neighbor_index];
+
+    let p = position_on_sphere(p, width, side);
+    let pb = position_on_sphere(b, width, bside);
+
+    // normal of the boundary, pointing towards the neighbor
+    let n = (pb - p).normalize();
+    if n.is_nan().any() {
+        return false;
+    }
+
+    let v = Vec3::cross(continent.rotation_axis, pb) * continent.speed;
+    let v_ = Vec3::cross(neighbor.rotation_axis, pb) * neighbor.speed;
+
+    let approach = Vec3::dot(v, n);
+    let approach_ = Vec3::dot(v_, -n);
+
+    let is_convergent = approach + approach_ > 0.0;
+    let is_overriding = approach < approach_;
+
+    is_convergent && is_overriding
+}
+
+pub fn position_on_sphere(texture_coordinate: (usize, usize), width: usize, side: Side) -> Vec3 {
     let (ix, iy) = texture_coordinate;
 
     // normalize texture coordinates
@@ -1435,67 +1775,443 @@ fn position_on_sphere(texture_coordinate: (usize, usize), width: usize, side: Si
     Vec3(sx, sy, sz)
 }
 
-fn random_gradient(ix: i32, iy: i32, seed: Seed) -> Vec2 {
-    let Seed(seed_value) = seed;
-    let seed_a = seed_value & 0xFFFFFFFF;
-    let seed_b = (seed_value >> 32) & 0xFFFFFFFF;
-
-    let w = (8 * std::mem::size_of::<u32>()) as u32;
-    let s = w / 2;
-    let a = (ix as u32) ^ (seed_a as u32);
-    let b = (iy as u32) ^ (seed_b as u32);
-    let a = a.wrapping_mul(3284157443);
-    let b = b ^ ((a << s) | (a >> (w - s)));
-    let b = b.wrapping_mul(1911520717);
-    let a = a ^ ((b << s) | (b >> (w - s)));
-    let a = a.wrapping_mul(2048419325);
-    let random = a as f32 * (PI / (!(!0u32 >> 1) as f32));
-    let v_x = f32::cos(random);
-    let v_y = f32::sin(random);
-    Vec2(v_x, v_y)
+/// returns the latitude of the pixel in radians, from -pi/2 at the south pole to pi/2 at the north
+/// pole. because of the cube distortion, rows of the side faces are not lines of constant
+/// latitude, so climate features must not derive the latitude from `y`.
+pub fn latitude_of(side: Side, x: usize, y: usize, width: usize) -> f32 {
+    let position = position_on_sphere((x, y), width, side);
+    f32::asin(position.normalize().z().clamp(-1.0, 1.0))
 }
 
-fn normalize(sides: &mut [ProtoSide], nan_replacement: Option<f32>) {
-    let mut min = f32::MAX;
-    let mut max = f32::MIN;
+// a very simple climate model: it is warm at the equator and cold at the poles, and the higher the
+// terrain, the colder it gets. returns 1 at the equator, 0 at the poles, minus `lapse_rate` times
+// the height.
+fn temperature(latitude: f32, height: f32, lapse_rate: f32) -> f32 {
+    f32::cos(latitude) - lapse_rate * height
+}
+
+struct GlacierArgs {
+    iterations: usize,
+    freezing_temperature: f32,
+    lapse_rate: f32,
+    accumulation: f32,
+    flow_rate: f32,
+    erode_speed: f32,
+}
+
+// ice accumulates where it is cold and melts where it is warm. each iteration, a fraction of the
+// ice flows to lower neighbors, proportional to how much lower the ice surface of the neighbor
+// is. the terrain below moving ice is eroded. since ice is thick and covers whole valleys, all of
+// the valley floor is eroded, not just its lowest point, producing U-shaped valleys.
+fn simulate_glaciers(sides: &[ProtoSide], width: usize, args: GlacierArgs) {
+    let GlacierArgs {
+        iterations,
+        freezing_temperature,
+        lapse_rate,
+        accumulation,
+        flow_rate,
+        erode_speed,
+    } = args;
 
+    let resolution = width * width;
+    let mut latitudes = Vec::with_capacity(sides.len() * resolution);
     for side in sides.iter() {
-        for h in side.height_map.borrow().values.iter() {
-            min = f32::min(min, h.height);
-            max = f32::max(max, h.height);
+        let side = side.height_map.borrow().side;
+        for iy in 0..width {
+            for ix in 0..width {
+                latitudes.push(latitude_of(side, ix, iy, width));
+            }
         }
     }
 
-    if min < max {
-        for side in sides.iter_mut() {
-            for h in side.height_map.borrow_mut().values.iter_mut() {
-                h.height = match (h.height.is_nan(), nan_replacement) {
-                    (true, Some(nan_replacement)) => nan_replacement,
-                    _ => (h.height - min) / (max - min),
-                };
+    let mut ice = vec![0.0; sides.len() * resolution];
+    let offsets = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+    for iteration in 0..iterations {
+        if iteration % 10 == 0 {
+            let progress = iteration as f32 / iterations as f32 * 100.0;
+            eprintln!("[7/8] simulate glaciers... {}%", progress);
+        }
+
+        // accumulate and melt
+        for (side_index, side) in sides.iter().enumerate() {
+            for (i, h) in side.height_map.borrow().values.iter().enumerate() {
+                let j = side_index * resolution + i;
+                let t = temperature(latitudes[j], h.height, lapse_rate);
+                if t < freezing_temperature {
+                    ice[j] += accumulation;
+                } else {
+                    ice[j] = f32::max(ice[j] - accumulation, 0.0);
+                }
             }
         }
-    }
 
-    eprintln!("normalized: {} {}", min, max);
-}
+        // flow and erode
+        let mut new_ice = ice.clone();
+        for (side_index, side) in sides.iter().enumerate() {
+            let side_ = side.height_map.borrow().side;
 
-fn gcd(mut a: usize, mut b: usize) -> usize {
-    while b != 0 {
-        let temp = b;
-        b = a % b;
-        a = temp;
-    }
+            for iy in 0..width {
+                for ix in 0..width {
+                    let j = side_index * resolution + iy * width + ix;
+                    if ice[j] <= 0.0 {
+                        continue;
+                    }
 
-    a
-}
+                    let surface = side.height_map.borrow().get(ix, iy).height + ice[j];
+
+                    let mut drops = [(0, 0.0); 4];
+                    let mut drop_sum = 0.0;
+                    for (k, offset) in offsets.iter().enumerate() {
+                        let RemappedIndex {
+                            ix: ix_,
+                            iy: iy_,
+                            side: side__,
+                        } = RemappedIndex::new(
+                            (ix as isize + offset.0, iy as isize + offset.1),
+                            width,
+                            side_,
+                        )
+                        .expect("offsets to not go over corners, only edges");
+
+                        let j_ = side__.to_index() * resolution + iy_ * width + ix_;
+                        let h_ = sides[side__.to_index()].height_map.borrow().get(ix_, iy_);
+                        let surface_ = h_.height + ice[j_];
+                        let drop = f32::max(surface - surface_, 0.0);
+
+                        drops[k] = (j_, drop);
+                        drop_sum += drop;
+                    }
 
-#[derive(Debug)]
-struct RemappedIndex {
-    ix: usize,
-    iy: usize,
-    side: Side,
-}
+                    if drop_sum <= 0.0 {
+                        continue;
+                    }
+
+                    let outflow = f32::min(ice[j] * flow_rate, drop_sum / 2.0);
+                    for (j_, drop) in drops {
+                        let flux = outflow * drop / drop_sum;
+                        new_ice[j] -= flux;
+                        new_ice[j_] += flux;
+                    }
+
+                    let mut h = side.height_map.borrow().get(ix, iy);
+                    h.height -= outflow * erode_speed;
+                    side.height_map.borrow_mut().set(ix, iy, h);
+                }
+            }
+        }
+
+        ice = new_ice;
+    }
+}
+
+fn random_gradient(ix: i32, iy: i32, seed: Seed) -> Vec2 {
+    let hash = crate::pcg::hash2(ix, iy, seed);
+    let random = hash as f32 * (PI / (!(!0u32 >> 1) as f32));
+    Vec2::from_angle(random)
+}
+
+// sums octaves of perlin noise on each side. the gradients on the edges and corners of the sides
+// are shared, such that the noise is continuous over the whole cube.
+fn add_perlin_noise(
+    sides: &[ProtoSide],
+    width: usize,
+    fractal_main_layer: usize,
+    fractal_weight: f32,
+    seed: Seed,
+    high_precision: bool,
+) {
+    let mut count = 0;
+    let layers = f32::log2(width as f32) as usize - 1;
+    let total = sides.len() * width * layers;
+    let mut accumulator = HeightAccumulator::new(sides, width, high_precision);
+
+    for side in sides.iter() {
+        let ProtoSide {
+            perlin_sampler,
+            height_map,
+        } = side;
+
+        let mut layer = 0;
+        loop {
+            let grid_width: i32 = 1 << (layer + 1);
+
+            // https://www.desmos.com/calculator/xnwqm8vdez
+            let a = 1.0;
+            let b = fractal_main_layer as f32;
+            let x = layer as f32;
+            let grid_weight = fractal_weight / (f32::abs(a * x - a * b) + 1.0);
+
+            layer += 1;
+
+            if grid_width >= width as i32 {
+                break;
+            }
+
+            for iy in 0..width {
+                if iy % 1000 == 0 {
+                    let process = (count as f32 / total as f32) * 100.0;
+                    eprintln!("[5/8] generating noise... {}%", process,);
+                }
+                count += 1;
+
+                for ix in 0..width {
+                    let coord = Vec2(ix as f32 + 0.5, iy as f32 + 0.5);
+                    let heigh_map_width = height_map.borrow().width as f32;
+                    let size = Vec2(heigh_map_width, heigh_map_width);
+                    let normalized = coord / size;
+                    let grid = Vec2(grid_width as f32, grid_width as f32);
+                    let p = normalized * grid;
+
+                    // this closure connects the edges and corners of different sizes, to
+                    // ensure that the perlin noise ist continuous over the whole cube
+                    let apply_net = |ix: i32, iy: i32| {
+                        let offset_x = perlin_sampler.offset.0 * grid_width;
+                        let offset_y = perlin_sampler.offset.1 * grid_width;
+                        let default_x = ix + offset_x;
+                        let default_y = iy + offset_y;
+                        let default = ((default_x, default_y), Mat2::identity());
+
+                        #[allow(clippy::if_same_then_else)]
+                        // justification: makes things easier to reason about. each branch is an
+                        // individual corner, edge or center pixel
+                        if ix == 0 {
+                            if iy == 0 {
+                                ((default_x, default_y), Mat2::init(0.0))
+                            } else if iy == grid_width {
+                                ((default_x, default_y), Mat2::init(0.0))
+                            } else {
+                                perlin_sampler
+                                    .edge0
+                                    .as_ref()
+                                    .map(|edge| edge(iy, (grid_width, grid_width)))
+                                    .unwrap_or(default)
+                            }
+                        } else if ix == grid_width {
+                            if iy == 0 {
+                                ((default_x, default_y), Mat2::init(0.0))
+                            } else if iy == grid_width {
+                                ((default_x, default_y), Mat2::init(0.0))
+                            } else {
+                                perlin_sampler
+                                    .edge1
+                                    .as_ref()
+                                    .map(|edge| edge(iy, (grid_width, grid_width)))
+                                    .unwrap_or(default)
+                            }
+                        } else if iy == 0 {
+                            perlin_sampler
+                                .edge2
+                                .as_ref()
+                                .map(|edge| edge(ix, (grid_width, grid_width)))
+                                .unwrap_or(default)
+                        } else if iy == grid_width {
+                            perlin_sampler
+                                .edge3
+                                .as_ref()
+                                .map(|edge| edge(ix, (grid_width, grid_width)))
+                                .unwrap_or(default)
+                        } else {
+                            default
+                        }
+                    };
+
+                    // perlin noise
+                    let m0 = p.x().floor() as i32;
+                    let m1 = m0 + 1;
+                    let n0 = p.y().floor() as i32;
+                    let n1 = n0 + 1;
+
+                    let (iq0, mat0) = apply_net(m0, n0);
+                    let (iq1, mat1) = apply_net(m1, n0);
+                    let (iq2, mat2) = apply_net(m0, n1);
+                    let (iq3, mat3) = apply_net(m1, n1);
+                    let g0 = mat0 * random_gradient(iq0.0, iq0.1, seed);
+                    let g1 = mat1 * random_gradient(iq1.0, iq1.1, seed);
+                    let g2 = mat2 * random_gradient(iq2.0, iq2.1, seed);
+                    let g3 = mat3 * random_gradient(iq3.0, iq3.1, seed);
+
+                    let q0 = Vec2(m0 as f32, n0 as f32);
+                    let q1 = Vec2(m1 as f32, n0 as f32);
+                    let q2 = Vec2(m0 as f32, n1 as f32);
+                    let q3 = Vec2(m1 as f32, n1 as f32);
+
+                    let s0 = g0.dot(p - q0);
+                    let s1 = g1.dot(p - q1);
+                    let s2 = g2.dot(p - q2);
+                    let s3 = g3.dot(p - q3);
+
+                    let h = |x: f32| (3.0 - x * 2.0) * x * x;
+                    let Vec2(x, y) = p - q0;
+                    let f0 = s0 * h(1.0 - x) + s1 * h(x);
+                    let f1 = s2 * h(1.0 - x) + s3 * h(x);
+                    let f = f0 * h(1.0 - y) + f1 * h(y);
+                    // perlin noise end
+
+                    accumulator.add(height_map, ix, iy, f * grid_weight);
+                }
+            }
+        }
+    } // end sides
+}
+
+// synthesizes a gaussian random field with a 1/f^beta power spectrum on each side, by applying
+// an inverse fft on frequency samples with random phase. since each side is generated
+// independently, the edges are blended afterwards, such that the noise is continuous over the
+// whole cube.
+fn add_spectral_noise(sides: &[ProtoSide], width: usize, beta: f32, weight: f32, rng: &mut Rng) {
+    let n = width.next_power_of_two();
+
+    let mut fields = Vec::with_capacity(sides.len());
+    for i in 0..sides.len() {
+        eprintln!(
+            "[5/8] generating spectral noise... {}/{}",
+            i + 1,
+            sides.len()
+        );
+
+        let mut re = vec![0.0; n * n];
+        let mut im = vec![0.0; n * n];
+
+        for v in 0..n {
+            for u in 0..n {
+                let fu = usize::min(u, n - u) as f32;
+                let fv = usize::min(v, n - v) as f32;
+                let f = f32::sqrt(fu * fu + fv * fv);
+
+                // power falls off with 1/f^beta, thus amplitude falls off with 1/f^(beta/2)
+                let amplitude = if f == 0.0 {
+                    0.0
+                } else {
+                    f32::powf(f, -beta / 2.0)
+                };
+                let phase = rng.next_f32_between(0.0, 2.0 * PI);
+
+                re[v * n + u] = amplitude * f32::cos(phase);
+                im[v * n + u] = amplitude * f32::sin(phase);
+            }
+        }
+
+        crate::fft::fft_2d(&mut re, &mut im, n, true)
+            .expect("n to be a power of two, because of next_power_of_two");
+
+        let mut field = Vec::with_capacity(width * width);
+        for iy in 0..width {
+            for ix in 0..width {
+                field.push(re[iy * n + ix]);
+            }
+        }
+
+        let max = field.iter().fold(0.0, |acc: f32, x| f32::max(acc, x.abs()));
+        if max > 0.0 {
+            for x in field.iter_mut() {
+                *x /= max;
+            }
+        }
+
+        fields.push(field);
+    }
+
+    // blend edges. each pixel close to an edge is mixed with its mirror on the other side of the
+    // edge. at the edge itself both pixels meet halfway, further away the own value dominates.
+    //
+    // pixels close to a corner lie in the band of two edges, and are blended with both mirrors.
+    // this is intended: both mirrors are read from the unblended `fields`, and each weight is at
+    // most 0.5, thus the result is a weighted average of the pixel and its two mirrors. blending
+    // with the nearest edge only would leave a seam along the diagonal of the corner.
+    eprintln!("[5/8] blending spectral noise edges...");
+    let band = usize::max(width / 8, 1);
+    let mut blended = fields.clone();
+    for (side_index, field) in blended.iter_mut().enumerate() {
+        let side = Side::from(side_index);
+
+        for iy in 0..width {
+            for ix in 0..width {
+                let edges = [
+                    (ix, (-1, 0)),
+                    (width - 1 - ix, (1, 0)),
+                    (iy, (0, -1)),
+                    (width - 1 - iy, (0, 1)),
+                ];
+
+                for (distance, direction) in edges {
+                    if distance >= band {
+                        continue;
+                    }
+
+                    let jump = (2 * distance + 1) as isize;
+                    let mirror = (
+                        ix as isize + direction.0 * jump,
+                        iy as isize + direction.1 * jump,
+                    );
+                    let Ok(RemappedIndex {
+                        ix: ix_,
+                        iy: iy_,
+                        side: side_,
+                    }) = RemappedIndex::new(mirror, width, side)
+                    else {
+                        continue;
+                    };
+
+                    let own = fields[side_index][iy * width + ix];
+                    let other = fields[side_.to_index()][iy_ * width + ix_];
+                    let t = 0.5 * (1.0 - distance as f32 / band as f32);
+
+                    let i = iy * width + ix;
+                    field[i] += (other - own) * t;
+                }
+            }
+        }
+    }
+
+    for (side, field) in sides.iter().zip(blended) {
+        for (h, f) in side.height_map.borrow_mut().values.iter_mut().zip(field) {
+            h.height += f * weight;
+        }
+    }
+}
+
+fn normalize(sides: &mut [ProtoSide], nan_replacement: Option<f32>) {
+    let mut min = f32::MAX;
+    let mut max = f32::MIN;
+
+    for side in sides.iter() {
+        for h in side.height_map.borrow().values.iter() {
+            min = f32::min(min, h.height);
+            max = f32::max(max, h.height);
+        }
+    }
+
+    if min < max {
+        for side in sides.iter_mut() {
+            for h in side.height_map.borrow_mut().values.iter_mut() {
+                h.height = match (h.height.is_nan(), nan_replacement) {
+                    (true, Some(nan_replacement)) => nan_replacement,
+                    _ => (h.height - min) / (max - min),
+                };
+            }
+        }
+    }
+
+    eprintln!("normalized: {} {}", min, max);
+}
+
+fn gcd(mut a: usize, mut b: usize) -> usize {
+    while b != 0 {
+        let temp = b;
+        b = a % b;
+        a = temp;
+    }
+
+    a
+}
+
+#[derive(Debug)]
+struct RemappedIndex {
+    ix: usize,
+    iy: usize,
+    side: Side,
+}
 
 impl RemappedIndex {
     fn new(i: (isize, isize), width: usize, side: Side) -> Result<Self, (Self, Self)> {
@@ -1644,17 +2360,13 @@ fn deposit_sediment(
     width: usize,
     side: Side,
     sides: &[ProtoSide],
+    accumulator: &mut HeightAccumulator,
     sediment: f32,
 ) {
     match RemappedIndex::new(ipos, width, side) {
         Ok(RemappedIndex { ix, iy, side }) => {
-            let side_index = side.to_index();
-            let mut h = sides[side_index].height_map.borrow().get(ix, iy);
-            h.height += sediment;
-            let side = &sides[side_index];
-            let height_map = &side.height_map;
-            let mut height_map = height_map.borrow_mut();
-            height_map.set(ix, iy, h);
+            let height_map = &sides[side.to_index()].height_map;
+            accumulator.add(height_map, ix, iy, sediment);
         }
         Err((
             RemappedIndex {
@@ -1671,8 +2383,647 @@ fn deposit_sediment(
             let li = (lix as isize, liy as isize);
             let ri = (rix as isize, riy as isize);
 
-            deposit_sediment(li, width, lside, sides, sediment / 2.0);
-            deposit_sediment(ri, width, rside, sides, sediment / 2.0);
+            deposit_sediment(li, width, lside, sides, accumulator, sediment / 2.0);
+            deposit_sediment(ri, width, rside, sides, accumulator, sediment / 2.0);
+        }
+    }
+}
+
+// same as `sample_height`, but for the finished height maps. `height_maps` must be ordered like
+// the result of `run`
+pub fn sample_height_map(
+    i: (isize, isize),
+    width: usize,
+    side: Side,
+    height_maps: &[HeightMap],
+) -> f32 {
+    let get = |RemappedIndex { ix, iy, side }: RemappedIndex| {
+        let height_map = &height_maps[side.to_index()];
+        debug_assert_eq!(height_map.side, side);
+        height_map.values[ix + iy * width]
+    };
+
+    match RemappedIndex::new(i, width, side) {
+        Ok(remapped) => get(remapped),
+        Err((lhs, rhs)) => (get(lhs) + get(rhs)) / 2.0,
+    }
+}
+
+/// samples the height maps at a fractional pixel position, by interpolating the 4 closest pixels
+/// bilinearly. pixels are looked up over the edges of the cube. `height_maps` must be ordered like
+/// the result of `run`.
+pub fn sample_height_map_bilinear(
+    p: (f32, f32),
+    width: usize,
+    side: Side,
+    height_maps: &[HeightMap],
+) -> f32 {
+    let (x, y) = p;
+    let ix = f32::floor(x);
+    let iy = f32::floor(y);
+    let tx = x - ix;
+    let ty = y - iy;
+    let ix = ix as isize;
+    let iy = iy as isize;
+
+    let h00 = sample_height_map((ix, iy), width, side, height_maps);
+    let h10 = sample_height_map((ix + 1, iy), width, side, height_maps);
+    let h01 = sample_height_map((ix, iy + 1), width, side, height_maps);
+    let h11 = sample_height_map((ix + 1, iy + 1), width, side, height_maps);
+
+    let top = crate::common::mix(h00, h10, tx);
+    let bottom = crate::common::mix(h01, h11, tx);
+    crate::common::mix(top, bottom, ty)
+}
+
+/// replaces each pixel with the median of its `(2 * radius + 1)²` neighborhood. the neighborhood
+/// wraps over the edges of the cube, such that no seams are introduced. `height_maps` must be
+/// ordered like the result of `run`.
+pub fn median_filter(height_maps: &mut [HeightMap], width: usize, radius: usize) {
+    let radius = usize::min(radius, width - 1) as isize;
+    let mut neighborhood = Vec::with_capacity(((2 * radius + 1) * (2 * radius + 1)) as usize);
+
+    let mut filtered = Vec::with_capacity(height_maps.len());
+    for height_map in height_maps.iter() {
+        let mut values = Vec::with_capacity(width * width);
+
+        for iy in 0..width as isize {
+            for ix in 0..width as isize {
+                neighborhood.clear();
+                for oy in -radius..=radius {
+                    for ox in -radius..=radius {
+                        let i = (ix + ox, iy + oy);
+                        let h = sample_height_map(i, width, height_map.side, height_maps);
+                        neighborhood.push(h);
+                    }
+                }
+
+                neighborhood.sort_by(f32::total_cmp);
+                values.push(neighborhood[neighborhood.len() / 2]);
+            }
+        }
+
+        filtered.push(values);
+    }
+
+    for (height_map, values) in height_maps.iter_mut().zip(filtered) {
+        height_map.values = values;
+    }
+}
+
+/// blurs the height maps with a gaussian kernel of standard deviation `sigma`. the kernel wraps
+/// over the edges of the cube, such that no seams are introduced. `height_maps` must be ordered
+/// like the result of `run`.
+pub fn gaussian_blur(height_maps: &mut [HeightMap], width: usize, sigma: f32) {
+    if sigma <= 0.0 {
+        return;
+    }
+
+    let radius = usize::min(f32::ceil(3.0 * sigma) as usize, width - 1) as isize;
+
+    let mut kernel = Vec::with_capacity(((2 * radius + 1) * (2 * radius + 1)) as usize);
+    let mut kernel_sum = 0.0;
+    for oy in -radius..=radius {
+        for ox in -radius..=radius {
+            let d2 = (ox * ox + oy * oy) as f32;
+            let weight = f32::exp(-d2 / (2.0 * sigma * sigma));
+            kernel.push(((ox, oy), weight));
+            kernel_sum += weight;
+        }
+    }
+
+    let mut blurred = Vec::with_capacity(height_maps.len());
+    for height_map in height_maps.iter() {
+        let mut values = Vec::with_capacity(width * width);
+
+        for iy in 0..width as isize {
+            for ix in 0..width as isize {
+                let mut sum = 0.0;
+                for &((ox, oy), weight) in kernel.iter() {
+                    let i = (ix + ox, iy + oy);
+                    sum += sample_height_map(i, width, height_map.side, height_maps) * weight;
+                }
+
+                values.push(sum / kernel_sum);
+            }
+        }
+
+        blurred.push(values);
+    }
+
+    for (height_map, values) in height_maps.iter_mut().zip(blurred) {
+        height_map.values = values;
+    }
+}
+
+/// sharpens the height maps by computing `height + amount * (height - blur(height))`, where
+/// `blur` is `gaussian_blur` with the given `sigma`. the result is clamped and normalized again.
+/// `height_maps` must be ordered like the result of `run`.
+pub fn unsharp_mask(height_maps: &mut [HeightMap], width: usize, sigma: f32, amount: f32) {
+    let mut blurred = height_maps.to_vec();
+    gaussian_blur(&mut blurred, width, sigma);
+
+    for (height_map, blurred) in height_maps.iter_mut().zip(blurred) {
+        for (h, b) in height_map.values.iter_mut().zip(blurred.values) {
+            *h = f32::clamp(*h + amount * (*h - b), 0.0, 1.0);
+        }
+    }
+
+    normalize_height_maps(height_maps);
+}
+
+// same as `normalize`, but for the finished height maps
+fn normalize_height_maps(height_maps: &mut [HeightMap]) {
+    let mut min = f32::MAX;
+    let mut max = f32::MIN;
+
+    for height_map in height_maps.iter() {
+        for &h in height_map.values.iter() {
+            min = f32::min(min, h);
+            max = f32::max(max, h);
+        }
+    }
+
+    if min < max {
+        for height_map in height_maps.iter_mut() {
+            for h in height_map.values.iter_mut() {
+                *h = (*h - min) / (max - min);
+            }
+        }
+    }
+}
+
+// returns the matrix, whose columns are the x and y axis of `side`, expressed in the frame of the
+// side that `i` is remapped to. since all sides are rotations of each other, the transpose
+// transforms vectors of the remapped side back into the frame of `side`. `i` must be at least one
+// pixel away from a corner, i.e. `RemappedIndex::new` must return `Ok`.
+fn remapped_frame(i: (isize, isize), width: usize, side: Side) -> Mat2 {
+    let (ix, iy) = i;
+    let w = width as isize;
+
+    // normal points away from `side`, tangent runs along the edge
+    let (normal, tangent) = if ix < 0 || ix >= w {
+        let sign = if ix < 0 { -1 } else { 1 };
+        let tangent_sign = if iy + 1 < w { 1 } else { -1 };
+        ((sign, 0), (0, tangent_sign))
+    } else if iy < 0 || iy >= w {
+        let sign = if iy < 0 { -1 } else { 1 };
+        let tangent_sign = if ix + 1 < w { 1 } else { -1 };
+        ((0, sign), (tangent_sign, 0))
+    } else {
+        return Mat2::identity();
+    };
+
+    let remap = |(ox, oy): (isize, isize)| {
+        let RemappedIndex { ix, iy, .. } = RemappedIndex::new((ix + ox, iy + oy), width, side)
+            .expect("index to not be in a corner");
+        Vec2(ix as f32, iy as f32)
+    };
+
+    let origin = remap((0, 0));
+    let image_normal = remap(normal) - origin;
+    let image_tangent = remap(tangent) - origin;
+
+    let (image_x, image_y) = if normal.0 != 0 {
+        (
+            image_normal * normal.0 as f32,
+            image_tangent * tangent.1 as f32,
+        )
+    } else {
+        (
+            image_tangent * tangent.0 as f32,
+            image_normal * normal.1 as f32,
+        )
+    };
+
+    Mat2(image_x, image_y)
+}
+
+// same as `RemappedIndex::new`, but for continuous positions, like the position of an erosion
+// droplet. returns `None` if `pos` lies on `side`. otherwise returns the position on the side that
+// `pos` wraps to, the new side, and the frame of the new side, see `remapped_frame`. only one edge
+// is crossed at a time. positions beyond a corner are wrapped over the x edge first, and must be
+// remapped again.
+//
+// a coordinate of exactly `width` lies on the seam and counts as on `side`. otherwise it would be
+// remapped onto the seam of the neighbouring side, which would remap it back.
+fn remap_position(pos: Vec2, width: usize, side: Side) -> Option<(Vec2, Side, Mat2)> {
+    let w = width as isize;
+    let index = |x: f32| {
+        if x == width as f32 {
+            w - 1
+        } else {
+            f32::floor(x) as isize
+        }
+    };
+    let ix = index(pos.x());
+    let iy = index(pos.y());
+
+    let i = if ix >= 0 && ix < w {
+        if iy >= 0 && iy < w {
+            return None;
+        }
+
+        (ix, iy)
+    } else {
+        (ix, isize::clamp(iy, 0, w - 1))
+    };
+
+    let RemappedIndex {
+        ix: new_ix,
+        iy: new_iy,
+        side: new_side,
+    } = RemappedIndex::new(i, width, side).expect("only one edge to be crossed");
+    let frame = remapped_frame(i, width, side);
+
+    // the remapping is a rotation around the center of the pixel
+    let center = Vec2(i.0 as f32 + 0.5, i.1 as f32 + 0.5);
+    let new_center = Vec2(new_ix as f32 + 0.5, new_iy as f32 + 0.5);
+    let new_pos = new_center + frame * (pos - center);
+
+    Some((new_pos, new_side, frame))
+}
+
+// remaps `pos` with `remap_position` until it lies on the returned side. also returns the product
+// of the frames of all crossed edges. a droplet moves less than 2 pixels per step, thus at most two
+// edges are crossed. returns `None` if the position does not settle, which would be a bug in the
+// remapping, but must not hang the erosion.
+fn wrap_position(pos: Vec2, width: usize, side: Side) -> Option<(Vec2, Side, Mat2)> {
+    const MAX_REMAPS: usize = 4;
+
+    let mut pos = pos;
+    let mut side = side;
+    let mut frame = Mat2::identity();
+
+    for _ in 0..MAX_REMAPS {
+        match remap_position(pos, width, side) {
+            None => return Some((pos, side, frame)),
+            Some((new_pos, new_side, new_frame)) => {
+                pos = new_pos;
+                side = new_side;
+                frame = new_frame * frame;
+            }
+        }
+    }
+
+    None
+}
+
+// samples a vector field, which is laid out like the result of `gradient_field`. vectors of other
+// sides are rotated into the frame of `side`
+fn sample_vector_field(i: (isize, isize), width: usize, side: Side, field: &[Vec2]) -> Vec2 {
+    let resolution = width * width;
+    let get = |i: (isize, isize)| {
+        let RemappedIndex {
+            ix,
+            iy,
+            side: side_,
+        } = RemappedIndex::new(i, width, side).expect("index to not be in a corner");
+        let v = field[side_.to_index() * resolution + ix + iy * width];
+        v * remapped_frame(i, width, side)
+    };
+
+    match RemappedIndex::new(i, width, side) {
+        Ok(_) => get(i),
+        Err(_) => {
+            let w = width as isize;
+            let clamped_ix = isize::clamp(i.0, 0, w - 1);
+            let clamped_iy = isize::clamp(i.1, 0, w - 1);
+            (get((clamped_ix, i.1)) + get((i.0, clamped_iy))) / 2.0
+        }
+    }
+}
+
+/// returns the gradient of the height maps, calculated with central differences. the result
+/// contains `width * width` vectors for each side, in the same order as `height_maps`. each vector
+/// is expressed in the frame of its own side, i.e. +x is right and +y is down. `height_maps` must
+/// be ordered like the result of `run`.
+pub fn gradient_field(height_maps: &[HeightMap], width: usize) -> Vec<Vec2> {
+    let mut result = Vec::with_capacity(height_maps.len() * width * width);
+
+    for height_map in height_maps.iter() {
+        let side = height_map.side;
+
+        for iy in 0..width as isize {
+            for ix in 0..width as isize {
+                let l = sample_height_map((ix - 1, iy), width, side, height_maps);
+                let r = sample_height_map((ix + 1, iy), width, side, height_maps);
+                let u = sample_height_map((ix, iy - 1), width, side, height_maps);
+                let d = sample_height_map((ix, iy + 1), width, side, height_maps);
+
+                result.push(Vec2((r - l) / 2.0, (d - u) / 2.0));
+            }
+        }
+    }
+
+    result
+}
+
+#[allow(dead_code)]
+// justification: public api. client code may use it for visualization and analysis
+/// returns the divergence of a vector field, which is laid out like the result of
+/// `gradient_field`. for the gradient of the terrain, positive values are valleys, where water
+/// collects, and negative values are ridges, where water originates.
+pub fn divergence(field: &[Vec2], width: usize) -> Vec<f32> {
+    let mut result = Vec::with_capacity(field.len());
+
+    for side_index in 0..field.len() / (width * width) {
+        let side = Side::from(side_index);
+
+        for iy in 0..width as isize {
+            for ix in 0..width as isize {
+                let l = sample_vector_field((ix - 1, iy), width, side, field);
+                let r = sample_vector_field((ix + 1, iy), width, side, field);
+                let u = sample_vector_field((ix, iy - 1), width, side, field);
+                let d = sample_vector_field((ix, iy + 1), width, side, field);
+
+                result.push((r.x() - l.x()) / 2.0 + (d.y() - u.y()) / 2.0);
+            }
+        }
+    }
+
+    result
+}
+
+#[allow(dead_code)]
+// justification: public api. client code may use it for visualization and analysis
+/// returns the curl of a vector field, which is laid out like the result of `gradient_field`.
+/// since a gradient is a conservative field, its curl is expected to be close to zero everywhere,
+/// including over the edges of the cube.
+pub fn curl(field: &[Vec2], width: usize) -> Vec<f32> {
+    let mut result = Vec::with_capacity(field.len());
+
+    for side_index in 0..field.len() / (width * width) {
+        let side = Side::from(side_index);
+
+        for iy in 0..width as isize {
+            for ix in 0..width as isize {
+                let l = sample_vector_field((ix - 1, iy), width, side, field);
+                let r = sample_vector_field((ix + 1, iy), width, side, field);
+                let u = sample_vector_field((ix, iy - 1), width, side, field);
+                let d = sample_vector_field((ix, iy + 1), width, side, field);
+
+                result.push((r.y() - l.y()) / 2.0 - (d.x() - u.x()) / 2.0);
+            }
+        }
+    }
+
+    result
+}
+
+// position on the sphere, displaced along its normal by the height. wraps over the edges of the
+// cube, such that neighboring pixels on different sides are found.
+fn displaced_position(
+    i: (isize, isize),
+    width: usize,
+    side: Side,
+    height_maps: &[HeightMap],
+    vertical_scale: VerticalScale,
+) -> Vec3 {
+    let get = |RemappedIndex { ix, iy, side }: RemappedIndex| {
+        let height_map = &height_maps[side.to_index()];
+        debug_assert_eq!(height_map.side, side);
+        let h = height_map.values[ix + iy * width];
+        vertical_scale.displace(position_on_sphere((ix, iy), width, side), h)
+    };
+
+    match RemappedIndex::new(i, width, side) {
+        Ok(remapped) => get(remapped),
+        Err((lhs, rhs)) => (get(lhs) + get(rhs)) / 2.0,
+    }
+}
+
+/// returns the normals of `side` in world space, i.e. on the sphere displaced by the height maps.
+/// the result contains `width * width` normals and can be used as one face of a cubemap. a
+/// `max_elevation` of 0 results in the normals of a perfect sphere. since neighbors are looked up
+/// over the edges of the cube, the normals are continuous over the seams. `height_maps` must be
+/// ordered like the result of `run`.
+#[allow(dead_code)]
+// justification: public api. client code may use it to light the sphere in a shader
+pub fn world_normal_map(
+    height_maps: &[HeightMap],
+    width: usize,
+    side: Side,
+    vertical_scale: VerticalScale,
+) -> Vec<Vec3> {
+    let mut result = Vec::with_capacity(width * width);
+
+    for iy in 0..width as isize {
+        for ix in 0..width as isize {
+            let l = displaced_position((ix - 1, iy), width, side, height_maps, vertical_scale);
+            let r = displaced_position((ix + 1, iy), width, side, height_maps, vertical_scale);
+            let u = displaced_position((ix, iy - 1), width, side, height_maps, vertical_scale);
+            let d = displaced_position((ix, iy + 1), width, side, height_maps, vertical_scale);
+
+            let p = position_on_sphere((ix as usize, iy as usize), width, side);
+            let n = Vec3::cross(r - l, d - u).normalize();
+
+            // the winding order depends on the side, make sure the normal points outwards
+            let n = if Vec3::dot(n, p) < 0.0 { -n } else { n };
+            result.push(n);
+        }
+    }
+
+    result
+}
+
+/// returns for each pixel the great-circle distance to the closest coastline, in radians on the
+/// unit sphere. values are negative in the ocean, i.e. below `sea_level`, and positive on land.
+/// pixels directly at the coast have a distance of 0. the result contains `width * width` values
+/// for each side, in the same order as `height_maps`. if there is no coastline, all values are
+/// infinite. `height_maps` must be ordered like the result of `run`.
+#[allow(dead_code)]
+// justification: public api. client code may use it to mask or shade coastal regions
+pub fn distance_to_coast(height_maps: &[HeightMap], width: usize, sea_level: f32) -> Vec<f32> {
+    let resolution = width * width;
+    let offsets = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+    let mut positions = Vec::with_capacity(height_maps.len() * resolution);
+    for height_map in height_maps.iter() {
+        for iy in 0..width {
+            for ix in 0..width {
+                positions.push(position_on_sphere((ix, iy), width, height_map.side));
+            }
+        }
+    }
+
+    let neighbor = |j: usize, offset: (isize, isize)| {
+        let side = Side::from(j / resolution);
+        let ix = (j % resolution % width) as isize;
+        let iy = (j % resolution / width) as isize;
+        let RemappedIndex {
+            ix: ix_,
+            iy: iy_,
+            side: side_,
+        } = RemappedIndex::new((ix + offset.0, iy + offset.1), width, side)
+            .expect("offsets to not go over corners, only edges");
+        side_.to_index() * resolution + iy_ * width + ix_
+    };
+
+    let is_land = |j: usize| height_maps[j / resolution].values[j % resolution] >= sea_level;
+
+    // dijkstra, originating from all coast pixels. instead of summing up the distances between
+    // neighbors, each pixel remembers its closest coast pixel. this approximates the true
+    // distance much better than counting steps. since all distances are positive, the bits of the
+    // f32 can be ordered like an integer.
+    let mut distances = vec![f32::INFINITY; height_maps.len() * resolution];
+    let mut sources = vec![usize::MAX; height_maps.len() * resolution];
+    let mut queue = std::collections::BinaryHeap::new();
+
+    for j in 0..distances.len() {
+        let is_coast = offsets
+            .iter()
+            .any(|&offset| is_land(j) != is_land(neighbor(j, offset)));
+
+        if is_coast {
+            distances[j] = 0.0;
+            sources[j] = j;
+            queue.push(std::cmp::Reverse((0.0f32.to_bits(), j)));
+        }
+    }
+
+    while let Some(std::cmp::Reverse((distance_bits, j))) = queue.pop() {
+        if distance_bits > distances[j].to_bits() {
+            continue;
+        }
+
+        let source = sources[j];
+        for offset in offsets {
+            let j_ = neighbor(j, offset);
+            let distance = Vec3::angle_between(positions[j_], positions[source]);
+
+            if distance < distances[j_] {
+                distances[j_] = distance;
+                sources[j_] = source;
+                queue.push(std::cmp::Reverse((distance.to_bits(), j_)));
+            }
+        }
+    }
+
+    for (j, distance) in distances.iter_mut().enumerate() {
+        if !is_land(j) {
+            *distance = -*distance;
+        }
+    }
+
+    distances
+}
+
+/// returns for each pixel a moisture value between 0 and 1. the ocean has a moisture of 1, and on
+/// land moisture decreases with the distance to the coast. wind blows around the axis `wind`, in
+/// the direction of `cross(wind, p)` for a point `p` on the sphere, similar to how continents
+/// move. air that has to climb over mountains loses its moisture, thus the lee side of a mountain
+/// range is drier than the windward side. `distance_to_coast` is the result of
+/// `distance_to_coast`. the result is ordered like `distance_to_coast`.
+#[allow(dead_code)]
+// justification: public api. client code may use it to classify biomes
+pub fn moisture_map(
+    distance_to_coast: &[f32],
+    wind: Vec3,
+    height_maps: &[HeightMap],
+    width: usize,
+) -> Vec<f32> {
+    // distance in radians, at which the moisture dropped to 1/e
+    const DISTANCE_FALLOFF: f32 = 0.2;
+    // how strongly a mountain upwind dries the air. the moisture is multiplied by
+    // exp(-RAIN_SHADOW * (upwind_height - height))
+    const RAIN_SHADOW: f32 = 8.0;
+
+    let resolution = width * width;
+    let max_steps = usize::max(width / 8, 1);
+
+    // project the wind onto the texture of each side, such that it can be followed pixel by pixel
+    let mut wind_directions = Vec::with_capacity(height_maps.len() * resolution);
+    for height_map in height_maps.iter() {
+        let side = height_map.side;
+        for iy in 0..width {
+            for ix in 0..width {
+                let p = position_on_sphere((ix, iy), width, side);
+                let tx = position_on_sphere((ix + 1, iy), width, side) - p;
+                let ty = position_on_sphere((ix, iy + 1), width, side) - p;
+
+                let w = Vec3::cross(wind, p);
+                let direction = Vec2(
+                    Vec3::dot(w, tx) / tx.length_squared(),
+                    Vec3::dot(w, ty) / ty.length_squared(),
+                )
+                .normalize();
+
+                wind_directions.push(direction);
+            }
+        }
+    }
+
+    let height = |j: usize| height_maps[j / resolution].values[j % resolution];
+
+    let mut moisture = vec![0.0; height_maps.len() * resolution];
+    for (j, value) in moisture.iter_mut().enumerate() {
+        let distance = distance_to_coast[j];
+        if distance <= 0.0 {
+            *value = 1.0;
+            continue;
+        }
+
+        let h = height(j);
+
+        // walk upwind until the ocean is reached, and remember the highest terrain on the way.
+        // fractional steps are accumulated, such that diagonal winds result in a diagonal walk
+        let mut ix = (j % resolution % width) as isize;
+        let mut iy = (j % resolution / width) as isize;
+        let mut side = Side::from(j / resolution);
+        let mut accumulated = Vec2(0.0, 0.0);
+        let mut upwind_height = h;
+
+        for _ in 0..max_steps {
+            let j_ = side.to_index() * resolution + iy as usize * width + ix as usize;
+            let direction = wind_directions[j_];
+            if direction.is_nan().any() {
+                break;
+            }
+
+            accumulated -= direction;
+            let step = Vec2(accumulated.x().round(), accumulated.y().round());
+            accumulated -= step;
+
+            let remapped = match RemappedIndex::new(
+                (ix + step.x() as isize, iy + step.y() as isize),
+                width,
+                side,
+            ) {
+                Ok(remapped) => remapped,
+                Err((remapped, _)) => remapped,
+            };
+
+            if remapped.side != side {
+                // the texture is oriented differently on the new side
+                accumulated = Vec2(0.0, 0.0);
+            }
+
+            ix = remapped.ix as isize;
+            iy = remapped.iy as isize;
+            side = remapped.side;
+
+            let j_ = side.to_index() * resolution + remapped.iy * width + remapped.ix;
+            if distance_to_coast[j_] <= 0.0 {
+                break;
+            }
+
+            upwind_height = f32::max(upwind_height, height(j_));
+        }
+
+        let coastal = f32::exp(-distance / DISTANCE_FALLOFF);
+        let shadow = f32::exp(-RAIN_SHADOW * (upwind_height - h));
+        *value = coastal * shadow;
+    }
+
+    moisture
+}
+
+// subtracts `rhs` from `lhs`, pixel by pixel
+fn subtract_height_maps(lhs: &mut [HeightMap], rhs: &[HeightMap]) {
+    for (lhs, rhs) in lhs.iter_mut().zip(rhs.iter()) {
+        for (l, r) in lhs.values.iter_mut().zip(rhs.values.iter()) {
+            *l -= r;
         }
     }
 }
@@ -1696,3 +3047,219 @@ fn prepare_proto_sides(sides: impl AsRef<[ProtoSide]>) -> Vec<HeightMap> {
 
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrap_position_settles_along_and_beyond_every_edge_and_corner() {
+        for width in [8, 64] {
+            let w = width as f32;
+            let along = [0.0, 0.25, 3.5, w / 2.0, w - 0.5, w - 0.0001, w];
+            let beyond = [0.0, 0.000_001, 0.5, 1.0, 1.49];
+
+            let mut points = Vec::new();
+            for &t in along.iter() {
+                for &d in beyond.iter() {
+                    points.push(Vec2(t, -d));
+                    points.push(Vec2(t, w + d));
+                    points.push(Vec2(-d, t));
+                    points.push(Vec2(w + d, t));
+                }
+            }
+            for &d in beyond.iter() {
+                points.push(Vec2(-d, -d));
+                points.push(Vec2(w + d, -d));
+                points.push(Vec2(-d, w + d));
+                points.push(Vec2(w + d, w + d));
+            }
+
+            for side in (0..6).map(Side::from) {
+                for &pos in points.iter() {
+                    let (new_pos, new_side, frame) = wrap_position(pos, width, side)
+                        .unwrap_or_else(|| panic!("{:?} on {:?} did not settle", pos, side));
+
+                    assert!(remap_position(new_pos, width, new_side).is_none());
+                    assert!(new_pos.x() >= 0.0 && new_pos.x() <= w, "{:?}", new_pos);
+                    assert!(new_pos.y() >= 0.0 && new_pos.y() <= w, "{:?}", new_pos);
+                    let ix = usize::min(new_pos.x() as usize, width - 1);
+                    let iy = usize::min(new_pos.y() as usize, width - 1);
+                    assert!(ix < width && iy < width);
+
+                    // frames must be rotations, such that `ErosionKernelOrigin::rotate` applies
+                    assert_eq!(frame.determinant(), 1.0, "{:?} on {:?}", pos, side);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn wrap_position_keeps_positions_on_the_seam() {
+        let (pos, side, _) = wrap_position(Vec2(26.261597, 64.0), 64, Side::F).unwrap();
+        assert_eq!((pos, side), (Vec2(26.261597, 64.0), Side::F));
+
+        let (pos, side, _) = wrap_position(Vec2(3.5, 8.0), 8, Side::F).unwrap();
+        assert_eq!((pos, side), (Vec2(3.5, 8.0), Side::F));
+    }
+
+    #[test]
+    fn continents_stay_connected_across_seams() {
+        let width = 8;
+        let sides = (0..6)
+            .map(|i| ProtoSide {
+                perlin_sampler: PerlinSampler {
+                    offset: (0, 0),
+                    edge0: None,
+                    edge1: None,
+                    edge2: None,
+                    edge3: None,
+                },
+                height_map: RefCell::new(ProtoHeightMap::new(Side::from(i), width)),
+            })
+            .collect::<Vec<_>>();
+
+        // one continent on the top edge of U, which is the top edge of F, one on the bottom edge
+        // of D, which is the bottom edge of F, and one in the middle of B to compete with them
+        let origins = [
+            ContinentPixel {
+                side: Side::U,
+                ix: 2,
+                iy: 0,
+            },
+            ContinentPixel {
+                side: Side::D,
+                ix: 5,
+                iy: width - 1,
+            },
+            ContinentPixel {
+                side: Side::B,
+                ix: 4,
+                iy: 4,
+            },
+        ];
+
+        let mut continents = origins
+            .iter()
+            .map(|origin| Continent {
+                origin: origin.clone(),
+                discovered_pixels: vec![origin.clone()],
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        let mut rng = Rng::new(Seed(42));
+        let mut growth_rngs = (0..continents.len())
+            .map(|_| rng.fork())
+            .collect::<Vec<_>>();
+
+        grow_continents(&mut continents, &mut growth_rngs, &sides, width, 0.0);
+
+        let continent_index = |pixel: &ContinentPixel| {
+            sides[pixel.side.to_index()]
+                .height_map
+                .borrow()
+                .get(pixel.ix, pixel.iy)
+                .continent_index
+        };
+        let neighbors = |pixel: &ContinentPixel| {
+            [(-1, 0), (1, 0), (0, -1), (0, 1)].map(|offset| {
+                let i = (pixel.ix as isize + offset.0, pixel.iy as isize + offset.1);
+                let RemappedIndex { ix, iy, side } =
+                    RemappedIndex::new(i, width, pixel.side).unwrap();
+                ContinentPixel { side, ix, iy }
+            })
+        };
+
+        let mut areas = vec![0; continents.len()];
+        for side in (0..6).map(Side::from) {
+            for iy in 0..width {
+                for ix in 0..width {
+                    let pixel = ContinentPixel { side, ix, iy };
+                    let index = continent_index(&pixel);
+                    assert!(index < continents.len(), "{:?} is unclaimed", pixel);
+                    areas[index] += 1;
+
+                    // the adjacency must be symmetric, otherwise a connected region on one face
+                    // would be disconnected when walking back from the other face
+                    for neighbor in neighbors(&pixel) {
+                        assert!(neighbors(&neighbor).contains(&pixel), "{:?}", pixel);
+                    }
+                }
+            }
+        }
+
+        for (i, origin) in origins.iter().enumerate() {
+            // flood fill from the origin, staying on the pixels of the continent
+            let mut visited = vec![origin.clone()];
+            let mut stack = vec![origin.clone()];
+            while let Some(pixel) = stack.pop() {
+                for neighbor in neighbors(&pixel) {
+                    if continent_index(&neighbor) == i && !visited.contains(&neighbor) {
+                        visited.push(neighbor.clone());
+                        stack.push(neighbor);
+                    }
+                }
+            }
+
+            assert_eq!(visited.len(), areas[i], "continent {} is split", i);
+        }
+
+        // the continents on the seams grew onto f
+        for (i, side) in [(0, Side::U), (0, Side::F), (1, Side::D), (1, Side::F)] {
+            let is_on_side = (0..width * width).any(|j| {
+                let pixel = ContinentPixel {
+                    side,
+                    ix: j % width,
+                    iy: j / width,
+                };
+                continent_index(&pixel) == i
+            });
+            assert!(is_on_side, "continent {} is not on {:?}", i, side);
+        }
+    }
+
+    #[test]
+    fn faster_continents_produce_higher_boundaries() {
+        let width = 8;
+        let continent = |ix, rotation_axis, speed| Continent {
+            origin: ContinentPixel {
+                side: Side::F,
+                ix,
+                iy: 4,
+            },
+            rotation_axis,
+            speed,
+            ..Default::default()
+        };
+
+        // two continents on f, moving towards each other
+        let p = position_on_sphere((3, 4), width, Side::F);
+        let p_ = position_on_sphere((4, 4), width, Side::F);
+        let height = |speed, speed_| {
+            let a = continent(0, Vec3(0.0, 0.0, 1.0), speed);
+            let b = continent(7, Vec3(0.0, 0.0, -1.0), speed_);
+            boundary_height(p, p_, &a, &b, width, BoundaryStyle::Sharp)
+        };
+
+        let slow = height(1.0, 1.0);
+        let fast = height(2.0, 2.0);
+        let mixed = height(1.0, 3.0);
+        assert!(slow > 0.0, "{}", slow);
+        assert!(fast > slow, "{} {}", fast, slow);
+        assert!((fast - 4.0 * slow).abs() < 0.000_1, "{} {}", fast, slow);
+        assert!((mixed - 3.0 * slow).abs() < 0.000_1, "{} {}", mixed, slow);
+
+        // a continent that doesn't move doesn't collide
+        assert_eq!(height(0.0, 1.0), 0.0);
+    }
+
+    #[test]
+    fn run_rejects_inverted_speed_range() {
+        let args = Args {
+            continent_min_speed: 2.0,
+            continent_max_speed: 1.0,
+            ..Default::default()
+        };
+        assert!(matches!(run(args), Err(TerrainError::InvalidSpeedRange)));
+    }
+}
@@ -2,6 +2,8 @@
 
 use crate::matrix::Mat2;
 
+const MIN_NORM: f32 = 0.000_001f32;
+
 //
 // definition
 //
@@ -43,6 +45,12 @@ impl Vec2 {
     pub fn one() -> Self {
         Self::init(1.0)
     }
+
+    /// returns the unit vector that is rotated counter clockwise from +x by `radians`
+    pub fn from_angle(radians: f32) -> Self {
+        let (sin, cos) = f32::sin_cos(radians);
+        Self(cos, sin)
+    }
 }
 
 impl From<[f32; 2]> for Vec2 {
@@ -139,6 +147,21 @@ impl From<Vec4> for [f32; 4] {
     }
 }
 
+/// appends w. use 1 for points and 0 for directions.
+impl From<(Vec3, f32)> for Vec4 {
+    fn from(value: (Vec3, f32)) -> Self {
+        let (v, w) = value;
+        Self(v.0, v.1, v.2, w)
+    }
+}
+
+/// drops w, without dividing by it.
+impl From<Vec4> for Vec3 {
+    fn from(value: Vec4) -> Self {
+        Self(value.0, value.1, value.2)
+    }
+}
+
 impl Bvec2 {
     pub fn init(value: bool) -> Self {
         Self(value, value)
@@ -283,6 +306,55 @@ impl Vec3 {
     pub fn set_p(&mut self, p: f32) {
         self.2 = p
     }
+
+    // swizzles
+    pub fn xy(self) -> Vec2 {
+        Vec2(self.0, self.1)
+    }
+
+    pub fn xz(self) -> Vec2 {
+        Vec2(self.0, self.2)
+    }
+
+    pub fn yx(self) -> Vec2 {
+        Vec2(self.1, self.0)
+    }
+
+    pub fn yz(self) -> Vec2 {
+        Vec2(self.1, self.2)
+    }
+
+    pub fn zx(self) -> Vec2 {
+        Vec2(self.2, self.0)
+    }
+
+    pub fn zy(self) -> Vec2 {
+        Vec2(self.2, self.1)
+    }
+
+    pub fn xyz(self) -> Self {
+        Self(self.0, self.1, self.2)
+    }
+
+    pub fn xzy(self) -> Self {
+        Self(self.0, self.2, self.1)
+    }
+
+    pub fn yxz(self) -> Self {
+        Self(self.1, self.0, self.2)
+    }
+
+    pub fn yzx(self) -> Self {
+        Self(self.1, self.2, self.0)
+    }
+
+    pub fn zxy(self) -> Self {
+        Self(self.2, self.0, self.1)
+    }
+
+    pub fn zyx(self) -> Self {
+        Self(self.2, self.1, self.0)
+    }
 }
 
 impl Vec4 {
@@ -1791,6 +1863,16 @@ impl Vec2 {
         )
     }
 
+    /// linearly interpolates between `a` and `b`. `t` is clamped to [0, 1].
+    pub fn lerp(a: Self, b: Self, t: f32) -> Self {
+        Self::lerp_unclamped(a, b, f32::clamp(t, 0.0, 1.0))
+    }
+
+    /// linearly interpolates between `a` and `b`. `t` outside of [0, 1] extrapolates.
+    pub fn lerp_unclamped(a: Self, b: Self, t: f32) -> Self {
+        Self::mix(a, b, Self::init(t))
+    }
+
     pub fn step(edge: Self, x: Self) -> Self {
         Self(
             crate::common::step(edge.0, x.0),
@@ -1894,6 +1976,16 @@ impl Vec3 {
         )
     }
 
+    /// linearly interpolates between `a` and `b`. `t` is clamped to [0, 1].
+    pub fn lerp(a: Self, b: Self, t: f32) -> Self {
+        Self::lerp_unclamped(a, b, f32::clamp(t, 0.0, 1.0))
+    }
+
+    /// linearly interpolates between `a` and `b`. `t` outside of [0, 1] extrapolates.
+    pub fn lerp_unclamped(a: Self, b: Self, t: f32) -> Self {
+        Self::mix(a, b, Self::init(t))
+    }
+
     pub fn step(edge: Self, x: Self) -> Self {
         Self(
             crate::common::step(edge.0, x.0),
@@ -2048,6 +2140,16 @@ impl Vec4 {
         )
     }
 
+    /// linearly interpolates between `a` and `b`. `t` is clamped to [0, 1].
+    pub fn lerp(a: Self, b: Self, t: f32) -> Self {
+        Self::lerp_unclamped(a, b, f32::clamp(t, 0.0, 1.0))
+    }
+
+    /// linearly interpolates between `a` and `b`. `t` outside of [0, 1] extrapolates.
+    pub fn lerp_unclamped(a: Self, b: Self, t: f32) -> Self {
+        Self::mix(a, b, Self::init(t))
+    }
+
     pub fn step(edge: Self, x: Self) -> Self {
         Self(
             crate::common::step(edge.0, x.0),
@@ -2123,6 +2225,17 @@ impl Vec2 {
         self / self.length()
     }
 
+    /// same as `normalize`, but returns the zero vector instead of NaN, if the length of self is
+    /// close to zero
+    pub fn normalize_or_zero(self) -> Self {
+        let length = self.length();
+        if length < MIN_NORM {
+            Self::zero()
+        } else {
+            self / length
+        }
+    }
+
     pub fn face_forward(self, i: Self, n_ref: Self) -> Self {
         if n_ref.dot(i) < 0. {
             self
@@ -2188,6 +2301,17 @@ impl Vec3 {
         self / self.length()
     }
 
+    /// same as `normalize`, but returns the zero vector instead of NaN, if the length of self is
+    /// close to zero
+    pub fn normalize_or_zero(self) -> Self {
+        let length = self.length();
+        if length < MIN_NORM {
+            Self::zero()
+        } else {
+            self / length
+        }
+    }
+
     pub fn face_forward(self, i: Self, n_ref: Self) -> Self {
         if n_ref.dot(i) < 0. {
             self
@@ -2245,6 +2369,17 @@ impl Vec4 {
         self / self.length()
     }
 
+    /// same as `normalize`, but returns the zero vector instead of NaN, if the length of self is
+    /// close to zero
+    pub fn normalize_or_zero(self) -> Self {
+        let length = self.length();
+        if length < MIN_NORM {
+            Self::zero()
+        } else {
+            self / length
+        }
+    }
+
     pub fn face_forward(self, i: Self, n_ref: Self) -> Self {
         if n_ref.dot(i) < 0. {
             self
@@ -2319,6 +2454,12 @@ impl Vec2 {
             f32::abs(self.1 - rhs.1) >= tolerance,
         )
     }
+
+    /// returns true if all components differ by less than `epsilon`. same as
+    /// `self.fequal(rhs, epsilon).all()`.
+    pub fn approx_eq(self, rhs: Self, epsilon: f32) -> bool {
+        self.fequal(rhs, epsilon).all()
+    }
 }
 
 impl Vec3 {
@@ -2361,6 +2502,12 @@ impl Vec3 {
             f32::abs(self.2 - rhs.2) >= tolerance,
         )
     }
+
+    /// returns true if all components differ by less than `epsilon`. same as
+    /// `self.fequal(rhs, epsilon).all()`.
+    pub fn approx_eq(self, rhs: Self, epsilon: f32) -> bool {
+        self.fequal(rhs, epsilon).all()
+    }
 }
 
 impl Vec4 {
@@ -2435,6 +2582,12 @@ impl Vec4 {
             f32::abs(self.3 - rhs.3) >= tolerance,
         )
     }
+
+    /// returns true if all components differ by less than `epsilon`. same as
+    /// `self.fequal(rhs, epsilon).all()`.
+    pub fn approx_eq(self, rhs: Self, epsilon: f32) -> bool {
+        self.fequal(rhs, epsilon).all()
+    }
 }
 
 impl std::cmp::PartialEq for Vec2 {
@@ -2489,15 +2642,51 @@ impl Bvec4 {
     }
 }
 
+//
+// 2d functions
+//
+
+impl Vec2 {
+    /// returns the counter clockwise angle from +x to self in radians, in [-PI, PI]. this is the
+    /// inverse of `from_angle`.
+    pub fn angle(self) -> f32 {
+        f32::atan2(self.1, self.0)
+    }
+
+    /// rotates by 90 degrees counter clockwise, i.e. turns +x into +y. same as
+    /// `Mat2::rotation(PI / 2.0) * self`, but exact.
+    pub fn rotate_ccw(self) -> Self {
+        Self(-self.1, self.0)
+    }
+
+    /// rotates by 90 degrees clockwise, i.e. turns +x into -y. same as
+    /// `Mat2::rotation(-PI / 2.0) * self`, but exact.
+    pub fn rotate_cw(self) -> Self {
+        Self(self.1, -self.0)
+    }
+
+    /// rotates by 180 degrees. same as `-self`.
+    pub fn rotate_180(self) -> Self {
+        Self(-self.0, -self.1)
+    }
+}
+
 //
 // 3d functions
 //
 
 impl Vec3 {
     pub fn angle(a: Self, b: Self) -> f32 {
-        let d = a.dot(b);
-        let m = a.length() * b.length();
-        f32::acos(d / m)
+        Self::angle_between(a, b)
+    }
+
+    /// returns the angle between a and b in radians, between 0 and pi. for unit vectors, this is
+    /// the great-circle distance on the unit sphere.
+    ///
+    /// unlike `acos(dot)`, this is numerically stable for nearly parallel and nearly opposite
+    /// vectors, and a and b don't need to be normalized.
+    pub fn angle_between(a: Self, b: Self) -> f32 {
+        f32::atan2(a.cross(b).length(), a.dot(b))
     }
 
     pub fn signed_angle(a: Self, b: Self, axis: Self) -> f32 {
@@ -2506,6 +2695,19 @@ impl Vec3 {
         theta * sign
     }
 
+    /// returns the length of the component of self that points along `onto`. negative if self
+    /// points away from `onto`. returns 0 if `onto` is close to zero.
+    pub fn scalar_projection(self, onto: Self) -> f32 {
+        self.dot(onto.normalize_or_zero())
+    }
+
+    /// returns the component of self that points along `onto`. returns the zero vector if `onto`
+    /// is close to zero.
+    pub fn project_onto(self, onto: Self) -> Self {
+        let n = onto.normalize_or_zero();
+        self.dot(n) * n
+    }
+
     /// returns the distance between the line ab and the point p
     pub fn distance_to_point(a: Self, b: Self, p: Self) -> f32 {
         let ab = b - a;
@@ -2514,3 +2716,182 @@ impl Vec3 {
         f32::sin(alpha) * ap.length()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cross_of_basis() {
+        let x = Vec3(1., 0., 0.);
+        let y = Vec3(0., 1., 0.);
+        let z = Vec3(0., 0., 1.);
+
+        assert_eq!(x.cross(y), z);
+        assert_eq!(y.cross(z), x);
+        assert_eq!(z.cross(x), y);
+    }
+
+    #[test]
+    fn cross_is_anticommutative() {
+        let a = Vec3(1., -2., 3.5);
+        let b = Vec3(0.5, 4., -1.);
+        assert_eq!(a.cross(b), -b.cross(a));
+    }
+
+    #[test]
+    fn vec4_arithmetic() {
+        let a = Vec4(1., 2., 3., 4.);
+        let b = Vec4(0.5, -1., 2., 0.);
+
+        assert_eq!(a + b, Vec4(1.5, 1., 5., 4.));
+        assert_eq!(a - b, Vec4(0.5, 3., 1., 4.));
+        assert_eq!(a * 2., Vec4(2., 4., 6., 8.));
+        assert_eq!(a / 2., Vec4(0.5, 1., 1.5, 2.));
+        assert_eq!(-a, Vec4(-1., -2., -3., -4.));
+        assert_eq!(a.dot(b), 4.5);
+        assert_eq!(Vec4(1., 2., 2., 4.).length(), 5.);
+        assert_eq!(Vec4::from((Vec3(1., 2., 3.), 4.)), a);
+        assert_eq!(Vec3::from(a), Vec3(1., 2., 3.));
+    }
+
+    #[test]
+    fn lerp_endpoints_and_midpoint() {
+        let a = Vec2(1., -2.);
+        let b = Vec2(3., 4.);
+        assert_eq!(Vec2::lerp(a, b, 0.), a);
+        assert_eq!(Vec2::lerp(a, b, 1.), b);
+        assert_eq!(Vec2::lerp(a, b, 0.5), Vec2(2., 1.));
+
+        let a = Vec3(1., -2., 0.);
+        let b = Vec3(3., 4., -8.);
+        assert_eq!(Vec3::lerp(a, b, 0.), a);
+        assert_eq!(Vec3::lerp(a, b, 1.), b);
+        assert_eq!(Vec3::lerp(a, b, 0.5), Vec3(2., 1., -4.));
+    }
+
+    #[test]
+    fn distances() {
+        let a = Vec3(1., 2., 3.);
+        let b = Vec3(3., 5., 9.);
+        assert_eq!(a.distance_squared(b), 49.);
+        assert_eq!(a.distance(b), 7.);
+        assert_eq!(b.distance(a), 7.);
+        assert_eq!(a.distance(a), 0.);
+    }
+
+    #[test]
+    fn min_max_clamp() {
+        let a = Vec2(1., 5.);
+        let b = Vec2(3., -2.);
+        assert_eq!(Vec2::min(a, b), Vec2(1., -2.));
+        assert_eq!(Vec2::max(a, b), Vec2(3., 5.));
+        assert_eq!(
+            Vec2(-1., 9.).clamp(Vec2(0., 0.), Vec2(4., 4.)),
+            Vec2(0., 4.)
+        );
+
+        let a = Vec3(1., 5., 0.);
+        let b = Vec3(3., -2., 0.);
+        assert_eq!(Vec3::min(a, b), Vec3(1., -2., 0.));
+        assert_eq!(Vec3::max(a, b), Vec3(3., 5., 0.));
+        assert_eq!(
+            Vec3(-1., 9., 2.).clamp(Vec3::init(0.), Vec3::init(4.)),
+            Vec3(0., 4., 2.)
+        );
+    }
+
+    #[test]
+    fn reflect_diagonal() {
+        let n = Vec3(0., 1., 0.);
+        assert_eq!(Vec3(1., -1., 0.).reflect(n), Vec3(1., 1., 0.));
+        assert_eq!(Vec3(-2., -2., 3.).reflect(n), Vec3(-2., 2., 3.));
+    }
+
+    #[test]
+    fn from_angle_and_angle_round_trip() {
+        assert_eq!(Vec2::from_angle(0.), Vec2(1., 0.));
+
+        for angle in [-3., -1.5, -0.2, 0., 0.7, 2., 3.1] {
+            let v = Vec2::from_angle(angle);
+            assert!((v.length() - 1.).abs() < 0.000_01);
+            assert!(
+                (v.angle() - angle).abs() < 0.000_01,
+                "{} {}",
+                v.angle(),
+                angle
+            );
+        }
+    }
+
+    #[test]
+    fn rotate_quarter_turns() {
+        let v = Vec2(3., -1.5);
+        assert_eq!(v.rotate_ccw().rotate_ccw().rotate_ccw().rotate_ccw(), v);
+        assert_eq!(v.rotate_cw().rotate_cw().rotate_cw().rotate_cw(), v);
+        assert_eq!(v.rotate_ccw().rotate_cw(), v);
+        assert_eq!(v.rotate_ccw().rotate_ccw(), v.rotate_180());
+        assert_eq!(Vec2(1., 0.).rotate_ccw(), Vec2(0., 1.));
+    }
+
+    #[test]
+    fn approx_eq_tolerance() {
+        assert!(Vec2(1., 2.).approx_eq(Vec2(1.05, 1.95), 0.1));
+        assert!(!Vec2(1., 2.).approx_eq(Vec2(1.05, 2.2), 0.1));
+        assert!(Vec3(1., 2., 3.).approx_eq(Vec3(1., 2., 3.000_001), 0.000_01));
+        assert!(!Vec3(1., 2., 3.).approx_eq(Vec3(1., 2., 3.1), 0.000_01));
+        assert!(Vec4(0., 0., 0., 0.).approx_eq(Vec4(0., 0., 0., 0.), 0.000_01));
+        assert!(!Vec4(0., 0., 0., 0.).approx_eq(Vec4(0., 0., 0., 1.), 0.5));
+    }
+
+    #[test]
+    fn normalize_or_zero_of_zero_is_zero() {
+        assert_eq!(Vec2::default().normalize_or_zero(), Vec2::default());
+        assert_eq!(Vec3::default().normalize_or_zero(), Vec3::default());
+        assert_eq!(Vec4::default().normalize_or_zero(), Vec4::default());
+        assert_eq!(Vec3(0., 3., 4.).normalize_or_zero(), Vec3(0., 0.6, 0.8));
+    }
+
+    #[test]
+    fn swizzles() {
+        let v = Vec3(1., 2., 3.);
+        assert_eq!(v.xy(), Vec2(1., 2.));
+        assert_eq!(v.xz(), Vec2(1., 3.));
+        assert_eq!(v.yx(), Vec2(2., 1.));
+        assert_eq!(v.yz(), Vec2(2., 3.));
+        assert_eq!(v.zx(), Vec2(3., 1.));
+        assert_eq!(v.zy(), Vec2(3., 2.));
+        assert_eq!(v.xyz(), v);
+        assert_eq!(v.xzy(), Vec3(1., 3., 2.));
+        assert_eq!(v.yxz(), Vec3(2., 1., 3.));
+        assert_eq!(v.yzx(), Vec3(2., 3., 1.));
+        assert_eq!(v.zxy(), Vec3(3., 1., 2.));
+        assert_eq!(v.zyx(), Vec3(3., 2., 1.));
+    }
+
+    #[test]
+    fn array_round_trip() {
+        let v = Vec2(1., -2.);
+        assert_eq!(<[f32; 2]>::from(v), [1., -2.]);
+        assert_eq!(Vec2::from(<[f32; 2]>::from(v)), v);
+
+        let v = Vec3(1., -2., 3.);
+        assert_eq!(<[f32; 3]>::from(v), [1., -2., 3.]);
+        assert_eq!(Vec3::from(<[f32; 3]>::from(v)), v);
+
+        let v = Vec4(1., -2., 3., -4.);
+        assert_eq!(<[f32; 4]>::from(v), [1., -2., 3., -4.]);
+        assert_eq!(Vec4::from(<[f32; 4]>::from(v)), v);
+    }
+
+    #[test]
+    fn projection_onto_axis() {
+        let v = Vec3(3., -4., 5.);
+        let axis = Vec3(0., 2., 0.);
+        assert_eq!(v.scalar_projection(axis), -4.);
+        assert_eq!(v.project_onto(axis), Vec3(0., -4., 0.));
+
+        assert_eq!(v.scalar_projection(Vec3::default()), 0.);
+        assert_eq!(v.project_onto(Vec3::default()), Vec3::default());
+    }
+}